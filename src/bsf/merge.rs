@@ -0,0 +1,81 @@
+//! Merging of hidden frames into super frames.
+
+use super::{BitstreamFilter, Packet};
+use crate::{
+    rewrite::{write_super_frame, MAX_FRAMES_IN_SUPER_FRAME},
+    Result, Vp9Parser, Vp9ParserError,
+};
+
+/// Merges packets with one frame each into super frames, like ffmpeg's `vp9_superframe`.
+///
+/// Every run of hidden frames is grouped with the next displayed frame into one super frame,
+/// which carries the timestamp of the displayed frame. Displayed frames without preceding
+/// hidden frames are passed through. A run of more than 7 hidden frames doesn't fit into one
+/// super frame, so every 8 hidden frames are emitted as a hidden super frame of their own.
+/// Packets that already end with a super frame index are rejected.
+#[derive(Clone, Debug, Default)]
+pub struct SuperFrameMerge {
+    parser: Vp9Parser,
+    pending: Vec<Vec<u8>>,
+    pending_timestamp: u64,
+}
+
+impl SuperFrameMerge {
+    /// Creates a new filter.
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+impl BitstreamFilter for SuperFrameMerge {
+    fn filter(&mut self, packet: Packet) -> Result<Vec<Packet>> {
+        let mut frames = self.parser.parse_packet(&packet.data);
+        if frames.is_super_frame() {
+            return Err(Vp9ParserError::UnexpectedSuperFrameIndex);
+        }
+
+        let hidden = match frames.next() {
+            Some(frame) => {
+                let frame = frame?;
+                !frame.show_existing_frame() && !frame.show_frame()
+            }
+            None => return Ok(Vec::new()),
+        };
+
+        self.pending.push(packet.data);
+        self.pending_timestamp = packet.timestamp;
+        if hidden {
+            if self.pending.len() < MAX_FRAMES_IN_SUPER_FRAME {
+                return Ok(Vec::new());
+            }
+            return self.flush();
+        }
+
+        let mut pending = std::mem::take(&mut self.pending);
+        let data = if pending.len() == 1 {
+            pending.remove(0)
+        } else {
+            write_super_frame(&pending)?
+        };
+
+        Ok(vec![Packet {
+            timestamp: packet.timestamp,
+            data,
+            hidden: false,
+        }])
+    }
+
+    /// Returns trailing hidden frames as one hidden packet with the timestamp of the last one.
+    fn flush(&mut self) -> Result<Vec<Packet>> {
+        if self.pending.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let data = write_super_frame(&std::mem::take(&mut self.pending))?;
+        Ok(vec![Packet {
+            timestamp: self.pending_timestamp,
+            data,
+            hidden: true,
+        }])
+    }
+}
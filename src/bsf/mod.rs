@@ -0,0 +1,51 @@
+//! Bitstream filters that transform VP9 packets without decoding them.
+//!
+//! All filters implement [`BitstreamFilter`], so they can be chained. Packets need to be
+//! supplied in bitstream order and [`BitstreamFilter::flush`] must be called at the end of
+//! the stream to drain packets a filter still holds back.
+
+pub use merge::SuperFrameMerge;
+pub use padding::{show_existing_frame, ConstantFrameRate};
+pub use reorder::RawReorder;
+pub use split::SuperFrameSplit;
+pub use thinning::{FrameThinning, ThinningMode};
+
+use crate::Result;
+
+mod merge;
+mod padding;
+mod reorder;
+mod split;
+mod thinning;
+
+/// A VP9 bitstream packet.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Packet {
+    /// The timestamp of the packet.
+    pub timestamp: u64,
+    /// The data of the packet.
+    pub data: Vec<u8>,
+    /// Indicates that the packet doesn't contain a frame that is displayed.
+    pub hidden: bool,
+}
+
+impl From<crate::ivf::Frame> for Packet {
+    fn from(frame: crate::ivf::Frame) -> Self {
+        Self {
+            timestamp: frame.timestamp,
+            data: frame.packet,
+            hidden: false,
+        }
+    }
+}
+
+/// A filter that transforms a stream of packets.
+pub trait BitstreamFilter {
+    /// Feeds a packet into the filter and returns the packets that are ready.
+    fn filter(&mut self, packet: Packet) -> Result<Vec<Packet>>;
+
+    /// Returns the packets the filter still holds back. Called at the end of the stream.
+    fn flush(&mut self) -> Result<Vec<Packet>> {
+        Ok(Vec::new())
+    }
+}
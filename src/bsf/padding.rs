@@ -0,0 +1,216 @@
+//! Frame duplication with synthesized `show_existing_frame` packets.
+
+use std::convert::TryFrom;
+
+use super::{BitstreamFilter, Packet};
+use crate::{ivf::Timebase, Profile, Result, Vp9Parser, Vp9ParserError};
+
+/// Builds a packet that displays the frame in the given reference slot again.
+///
+/// The packet only contains an uncompressed header with `show_existing_frame` set. It's one
+/// byte long, except for profile 3, which needs an additional reserved bit.
+pub fn show_existing_frame(profile: Profile, frame_to_show_map_idx: u8) -> Result<Vec<u8>> {
+    if frame_to_show_map_idx > 7 {
+        return Err(Vp9ParserError::InvalidHeaderValue("frame_to_show_map_idx"));
+    }
+
+    // frame_marker, profile_low_bit, profile_high_bit, show_existing_frame, frame_to_show_map_idx
+    let header = match profile {
+        Profile::Profile0 => 0b1000_1000 | frame_to_show_map_idx,
+        Profile::Profile1 => 0b1010_1000 | frame_to_show_map_idx,
+        Profile::Profile2 => 0b1001_1000 | frame_to_show_map_idx,
+        Profile::Profile3 => {
+            // An additional reserved_zero bit follows the profile.
+            let bits = 0b1011_0100_0000_0000 | (u16::from(frame_to_show_map_idx) << 7);
+            return Ok(bits.to_be_bytes().to_vec());
+        }
+        Profile::Unknown => return Err(Vp9ParserError::InvalidHeaderValue("profile")),
+    };
+
+    Ok(vec![header])
+}
+
+/// Pads a stream to a constant frame rate by repeating the last displayed frame.
+///
+/// The target frame rate is `frame_rate_rate / frame_rate_scale` frames per second and the
+/// input timestamps use the given timebase. Timestamps are placed on a grid that starts at the
+/// first displayed frame. The grid is calculated exactly and only the timestamp of each frame
+/// is rounded to the timebase, so targets like 50 fps in a timebase of 1/30 seconds don't
+/// drift. If a frame is shorter than a tick, consecutive packets can share a timestamp.
+///
+/// Gaps in the input are filled with `show_existing_frame` packets. Frames are never dropped,
+/// so a source that is faster than the target frame rate drifts behind; use
+/// [`super::FrameThinning`] first in such cases.
+///
+/// The filter tracks the reference slots to know which slot still holds the last displayed
+/// picture. If no slot holds it, for example because the frame didn't refresh any slot,
+/// the gap can't be filled.
+#[derive(Clone, Debug)]
+pub struct ConstantFrameRate {
+    parser: Vp9Parser,
+    // The frame duration in ticks is `duration_dividend / duration_divisor`.
+    duration_dividend: u128,
+    duration_divisor: u128,
+    origin: Option<u64>,
+    // The index of the next frame on the grid.
+    next_frame: u64,
+    profile: Profile,
+    // An identifier of the picture each reference slot holds.
+    slots: [Option<u64>; 8],
+    next_picture: u64,
+    last_displayed: Option<u64>,
+}
+
+impl ConstantFrameRate {
+    /// Creates a new filter. Zero values of the frame rate or the timebase are treated as one.
+    pub fn new(timebase: Timebase, frame_rate_rate: u32, frame_rate_scale: u32) -> Self {
+        let duration_dividend =
+            u128::from(frame_rate_scale.max(1)) * u128::from(timebase.denominator.max(1));
+        let duration_divisor =
+            u128::from(frame_rate_rate.max(1)) * u128::from(timebase.numerator.max(1));
+
+        Self {
+            parser: Vp9Parser::default(),
+            duration_dividend,
+            duration_divisor,
+            origin: None,
+            next_frame: 0,
+            profile: Profile::Profile0,
+            slots: [None; 8],
+            next_picture: 0,
+            last_displayed: None,
+        }
+    }
+
+    /// The slot that holds the last displayed picture.
+    fn last_displayed_slot(&self) -> Option<u8> {
+        let picture = self.last_displayed?;
+        let slot = self.slots.iter().position(|slot| *slot == Some(picture))?;
+        slot.try_into().ok()
+    }
+
+    /// Returns the index of the grid frame closest to the timestamp.
+    fn grid_frame(&self, origin: u64, timestamp: u64) -> u64 {
+        let offset = u128::from(timestamp.saturating_sub(origin));
+        let frames = offset
+            .saturating_mul(self.duration_divisor)
+            .saturating_add(self.duration_dividend / 2)
+            / self.duration_dividend;
+        u64::try_from(frames).unwrap_or(u64::MAX)
+    }
+
+    /// Returns the timestamp of a grid frame, rounded to the nearest tick.
+    fn frame_timestamp(&self, origin: u64, frame: u64) -> u64 {
+        let ticks = u128::from(frame)
+            .saturating_mul(self.duration_dividend)
+            .saturating_add(self.duration_divisor / 2)
+            / self.duration_divisor;
+        u64::try_from(ticks.saturating_add(u128::from(origin))).unwrap_or(u64::MAX)
+    }
+}
+
+impl BitstreamFilter for ConstantFrameRate {
+    fn filter(&mut self, packet: Packet) -> Result<Vec<Packet>> {
+        let repeat_slot = self.last_displayed_slot();
+        let repeat_profile = self.profile;
+
+        let mut displayed = false;
+        for frame in self.parser.parse_packet(&packet.data) {
+            let frame = frame?;
+            self.profile = frame.profile();
+
+            if let Some(index) = frame.frame_to_show_map_idx() {
+                self.last_displayed = self.slots.get(usize::from(index)).copied().flatten();
+                displayed = true;
+                continue;
+            }
+
+            let picture = self.next_picture;
+            self.next_picture += 1;
+            let flags = frame.refresh_frame_flags();
+            for (i, slot) in self.slots.iter_mut().enumerate() {
+                if (flags >> i) & 1 == 1 {
+                    *slot = Some(picture);
+                }
+            }
+
+            if frame.show_frame() {
+                self.last_displayed = Some(picture);
+                displayed = true;
+            }
+        }
+
+        if !displayed {
+            let timestamp = match self.origin {
+                Some(origin) => self.frame_timestamp(origin, self.next_frame),
+                None => packet.timestamp,
+            };
+            return Ok(vec![Packet {
+                timestamp,
+                hidden: true,
+                ..packet
+            }]);
+        }
+
+        let origin = match self.origin {
+            Some(origin) => origin,
+            None => {
+                self.origin = Some(packet.timestamp);
+                self.next_frame = 0;
+                packet.timestamp
+            }
+        };
+        let frame = self
+            .grid_frame(origin, packet.timestamp)
+            .max(self.next_frame);
+
+        let mut packets = Vec::new();
+        if let Some(slot) = repeat_slot {
+            while self.next_frame < frame {
+                packets.push(Packet {
+                    timestamp: self.frame_timestamp(origin, self.next_frame),
+                    data: show_existing_frame(repeat_profile, slot)?,
+                    hidden: false,
+                });
+                self.next_frame = self.next_frame.saturating_add(1);
+            }
+        }
+
+        packets.push(Packet {
+            timestamp: self.frame_timestamp(origin, frame),
+            hidden: false,
+            ..packet
+        });
+        self.next_frame = frame.saturating_add(1);
+
+        Ok(packets)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+
+    #[test]
+    fn build_show_existing_frame() {
+        for profile in [
+            Profile::Profile0,
+            Profile::Profile1,
+            Profile::Profile2,
+            Profile::Profile3,
+        ] {
+            let data = show_existing_frame(profile, 5).unwrap();
+            assert_eq!(data.len(), if profile == Profile::Profile3 { 2 } else { 1 });
+
+            let mut parser = Vp9Parser::default();
+            let frame = parser.parse_packet(&data).next().unwrap().unwrap();
+            assert_eq!(frame.profile(), profile);
+            assert!(frame.show_existing_frame());
+            assert_eq!(frame.frame_to_show_map_idx(), Some(5));
+        }
+
+        assert!(show_existing_frame(Profile::Profile0, 8).is_err());
+    }
+}
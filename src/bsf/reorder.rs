@@ -0,0 +1,127 @@
+//! Reordering of packets into one displayed frame per packet.
+
+use super::{BitstreamFilter, Packet};
+use crate::{rewrite::write_super_frame, Result, Vp9Parser};
+
+/// Reorders a stream so that every packet contains exactly one displayed frame,
+/// like ffmpeg's `vp9_raw_reorder`.
+///
+/// Hidden frames are grouped with the next displayed frame, either a shown frame or a
+/// `show_existing_frame` that references a reference slot. Packets that contain more than one
+/// displayed frame are split up and the timestamps of the displayed frames are interpolated
+/// between the timestamp of the packet and the timestamp of the next packet. A
+/// `show_existing_frame` that references a slot which has never been filled, for example after
+/// starting in the middle of a stream, is dropped.
+///
+/// The filter holds back the packets of the last input packet until the next one arrives.
+#[derive(Clone, Debug, Default)]
+pub struct RawReorder {
+    parser: Vp9Parser,
+    filled_slots: [bool; 8],
+    hidden: Vec<Vec<u8>>,
+    hidden_timestamp: u64,
+    queued: Vec<Vec<u8>>,
+    queued_timestamp: u64,
+    last_duration: u64,
+}
+
+impl RawReorder {
+    /// Creates a new filter.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Returns the queued packets with timestamps spread over `duration`.
+    fn drain_queued(&mut self, duration: u64) -> Vec<Packet> {
+        let count = u64::try_from(self.queued.len()).unwrap_or(u64::MAX);
+        let start = self.queued_timestamp;
+        self.queued
+            .drain(..)
+            .zip(0u64..)
+            .map(|(data, index)| Packet {
+                timestamp: start.saturating_add(duration.saturating_mul(index) / count),
+                data,
+                hidden: false,
+            })
+            .collect()
+    }
+}
+
+impl BitstreamFilter for RawReorder {
+    fn filter(&mut self, packet: Packet) -> Result<Vec<Packet>> {
+        let mut displayed = Vec::new();
+        for frame in self.parser.parse_packet(&packet.data) {
+            let frame = frame?;
+
+            if let Some(index) = frame.frame_to_show_map_idx() {
+                let filled = self
+                    .filled_slots
+                    .get(usize::from(index))
+                    .copied()
+                    .unwrap_or_default();
+                if !filled {
+                    continue;
+                }
+            } else {
+                let flags = frame.refresh_frame_flags();
+                for (i, slot) in self.filled_slots.iter_mut().enumerate() {
+                    if (flags >> i) & 1 == 1 {
+                        *slot = true;
+                    }
+                }
+            }
+
+            let shown = frame.show_existing_frame() || frame.show_frame();
+            self.hidden.push(frame.into_vec());
+            if !shown {
+                self.hidden_timestamp = packet.timestamp;
+                continue;
+            }
+
+            if self.hidden.len() == 1 {
+                displayed.append(&mut self.hidden);
+            } else {
+                displayed.push(write_super_frame(&self.hidden)?);
+                self.hidden.clear();
+            }
+        }
+
+        if displayed.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut packets = Vec::new();
+        if !self.queued.is_empty() {
+            let duration = packet.timestamp.saturating_sub(self.queued_timestamp);
+            let count = u64::try_from(self.queued.len()).unwrap_or(u64::MAX);
+            self.last_duration = duration / count;
+            packets = self.drain_queued(duration);
+        }
+
+        self.queued = displayed;
+        self.queued_timestamp = packet.timestamp;
+
+        Ok(packets)
+    }
+
+    /// Returns the held back packets. The last displayed frames are assumed to have the
+    /// same duration as the frames before. If no duration is known, for example when the
+    /// stream is a single packet, every frame lasts one tick of the timebase. Trailing hidden
+    /// frames are returned as one hidden packet.
+    fn flush(&mut self) -> Result<Vec<Packet>> {
+        let count = u64::try_from(self.queued.len()).unwrap_or(u64::MAX);
+        let duration = self.last_duration.max(1).saturating_mul(count);
+        let mut packets = self.drain_queued(duration);
+
+        if !self.hidden.is_empty() {
+            packets.push(Packet {
+                timestamp: self.hidden_timestamp,
+                data: write_super_frame(&self.hidden)?,
+                hidden: true,
+            });
+            self.hidden.clear();
+        }
+
+        Ok(packets)
+    }
+}
@@ -0,0 +1,37 @@
+//! Splitting of super frames.
+
+use super::{BitstreamFilter, Packet};
+use crate::{Result, Vp9Parser};
+
+/// Splits super frames into one packet per frame, like ffmpeg's `vp9_superframe_split`.
+///
+/// The super frame index is stripped. Every packet carries the timestamp of the packet it
+/// originated from and frames that are not displayed are flagged as hidden.
+#[derive(Clone, Debug, Default)]
+pub struct SuperFrameSplit {
+    parser: Vp9Parser,
+}
+
+impl SuperFrameSplit {
+    /// Creates a new filter.
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+impl BitstreamFilter for SuperFrameSplit {
+    fn filter(&mut self, packet: Packet) -> Result<Vec<Packet>> {
+        let mut packets = Vec::new();
+        for frame in self.parser.parse_packet(&packet.data) {
+            let frame = frame?;
+            let hidden = !frame.show_existing_frame() && !frame.show_frame();
+            packets.push(Packet {
+                timestamp: packet.timestamp,
+                data: frame.into_vec(),
+                hidden,
+            });
+        }
+
+        Ok(packets)
+    }
+}
@@ -0,0 +1,330 @@
+//! Dropping of frames that are not referenced.
+
+use std::collections::VecDeque;
+
+use super::{BitstreamFilter, Packet};
+use crate::{rewrite::write_super_frame, Frame, FrameType, ResetFrameContext, Result, Vp9Parser};
+
+/// Defines which frames the [`FrameThinning`] filter drops.
+#[derive(Clone, Copy, Debug, Ord, PartialOrd, Eq, PartialEq)]
+pub enum ThinningMode {
+    /// Drops frames that refresh neither a reference slot nor a probability context.
+    NonReference,
+    /// Additionally drops frames whose refreshed slots and probability contexts are overwritten
+    /// before any frame uses them. Packets are held back until this can be decided.
+    Unreferenced,
+}
+
+/// Drops frames that no other frame depends on, which lowers the frame rate without
+/// re-encoding.
+///
+/// Besides the reference slots and the probability contexts, an inter frame can use the motion
+/// vectors and the segmentation map of the previous frame if it has the same size, isn't error
+/// resilient and the previous frame was shown and not intra-only. A frame is only dropped if
+/// the next frame can't do so, neither with the dropped frame nor with the frame that takes its
+/// place. Streams encoded without error resilience, like the default output of libvpx, chain
+/// every inter frame to the previous one this way, so frames are only dropped in front of key
+/// frames, intra-only or error resilient frames and frame size changes. Streams with temporal
+/// layers, which are usually error resilient, are thinned much further.
+///
+/// Super frames are rebuilt from the remaining frames and packets without remaining frames are
+/// removed. Timestamps are kept.
+#[derive(Clone, Debug)]
+pub struct FrameThinning {
+    parser: Vp9Parser,
+    mode: ThinningMode,
+    queue: VecDeque<PendingPacket>,
+    // The state of the last kept frame that left the queue.
+    last_kept: Option<FrameState>,
+}
+
+#[derive(Clone, Debug)]
+struct PendingPacket {
+    timestamp: u64,
+    frames: Vec<PendingFrame>,
+}
+
+#[derive(Clone, Debug)]
+struct PendingFrame {
+    data: Vec<u8>,
+    shown: bool,
+    keep: Option<bool>,
+    // The slots that still hold this frame and haven't been referenced.
+    slots: u8,
+    // The probability contexts that still hold the probabilities of this frame and haven't
+    // been loaded.
+    contexts: u8,
+    // `None` for frames that show an existing frame.
+    state: Option<FrameState>,
+    // Set once the next decoded frame is known not to use the state of this frame.
+    next_frame_checked: bool,
+}
+
+/// The state of a decoded frame that the next frame can use.
+#[derive(Clone, Copy, Debug)]
+struct FrameState {
+    width: u16,
+    height: u16,
+    shown: bool,
+    intra_only: bool,
+}
+
+impl FrameThinning {
+    /// Creates a new filter.
+    pub fn new(mode: ThinningMode) -> Self {
+        Self {
+            parser: Vp9Parser::default(),
+            mode,
+            queue: VecDeque::new(),
+            last_kept: None,
+        }
+    }
+
+    /// Keeps the previous frame if the frame can use the state of any frame that might
+    /// precede it once undecided frames are dropped.
+    fn check_previous_frame(
+        queue: &mut VecDeque<PendingPacket>,
+        last_kept: Option<FrameState>,
+        frame: &Frame,
+    ) {
+        if frame.show_existing_frame() {
+            return;
+        }
+
+        let mut uses_previous = false;
+        let mut shown_later = false;
+        let mut found_kept = false;
+        let frames = queue
+            .iter()
+            .rev()
+            .flat_map(|packet| packet.frames.iter().rev());
+        for pending in frames.filter(|pending| pending.keep != Some(false)) {
+            match pending.state {
+                // Be conservative about frames that are shown with show_existing_frame.
+                None => shown_later = true,
+                Some(state) => {
+                    uses_previous |= uses_previous_state(frame, state, shown_later);
+                    if pending.keep == Some(true) {
+                        found_kept = true;
+                        break;
+                    }
+                }
+            }
+        }
+        if !found_kept {
+            if let Some(state) = last_kept {
+                uses_previous |= uses_previous_state(frame, state, shown_later);
+            }
+        }
+
+        let frames = queue.iter_mut().flat_map(|packet| packet.frames.iter_mut());
+        for pending in frames.filter(|pending| !pending.next_frame_checked) {
+            pending.next_frame_checked = true;
+            if uses_previous {
+                let _ = pending.keep.get_or_insert(true);
+            }
+        }
+    }
+
+    /// Updates the undecided frames with the slots and contexts the frame uses and refreshes.
+    fn update_pending(queue: &mut VecDeque<PendingPacket>, frame: &Frame) {
+        let referenced = referenced_slots(frame);
+        let refreshed = refreshed_slots(frame);
+        let loaded = loaded_contexts(frame);
+        let saved = saved_contexts(frame);
+
+        let frames = queue.iter_mut().flat_map(|packet| packet.frames.iter_mut());
+        for pending in frames.filter(|pending| pending.keep.is_none()) {
+            if pending.slots & referenced != 0 || pending.contexts & loaded != 0 {
+                pending.keep = Some(true);
+                continue;
+            }
+
+            pending.slots &= !refreshed;
+            pending.contexts &= !saved;
+            if pending.slots == 0 && pending.contexts == 0 && pending.next_frame_checked {
+                pending.keep = Some(false);
+            }
+        }
+    }
+
+    fn pending_frame(mode: ThinningMode, frame: Frame) -> PendingFrame {
+        let slots = refreshed_slots(&frame);
+        let contexts = saved_contexts(&frame);
+
+        let keep = if mode == ThinningMode::NonReference && (slots != 0 || contexts != 0) {
+            Some(true)
+        } else {
+            None
+        };
+
+        let state = (!frame.show_existing_frame()).then(|| FrameState {
+            width: frame.width(),
+            height: frame.height(),
+            shown: frame.show_frame(),
+            intra_only: frame.intra_only(),
+        });
+
+        PendingFrame {
+            shown: frame.show_existing_frame() || frame.show_frame(),
+            keep,
+            slots,
+            contexts,
+            state,
+            next_frame_checked: false,
+            data: frame.into_vec(),
+        }
+    }
+
+    /// Returns the packets of which all frames are decided.
+    fn drain_decided(&mut self) -> Result<Vec<Packet>> {
+        let mut packets = Vec::new();
+        while let Some(packet) = self.queue.front() {
+            if packet.frames.iter().any(|frame| frame.keep.is_none()) {
+                break;
+            }
+
+            if let Some(packet) = self.queue.pop_front() {
+                let last_kept = packet
+                    .frames
+                    .iter()
+                    .rev()
+                    .filter(|frame| frame.keep == Some(true))
+                    .find_map(|frame| frame.state);
+                if last_kept.is_some() {
+                    self.last_kept = last_kept;
+                }
+
+                if let Some(packet) = assemble(packet)? {
+                    packets.push(packet);
+                }
+            }
+        }
+
+        Ok(packets)
+    }
+}
+
+impl BitstreamFilter for FrameThinning {
+    fn filter(&mut self, packet: Packet) -> Result<Vec<Packet>> {
+        self.queue.push_back(PendingPacket {
+            timestamp: packet.timestamp,
+            frames: Vec::new(),
+        });
+
+        for frame in self.parser.parse_packet(&packet.data) {
+            let frame = frame?;
+            Self::check_previous_frame(&mut self.queue, self.last_kept, &frame);
+            Self::update_pending(&mut self.queue, &frame);
+            if let Some(pending) = self.queue.back_mut() {
+                pending.frames.push(Self::pending_frame(self.mode, frame));
+            }
+        }
+
+        self.drain_decided()
+    }
+
+    /// Keeps all frames that are still undecided, since frames after the end of the stream
+    /// might reference them.
+    fn flush(&mut self) -> Result<Vec<Packet>> {
+        for packet in self.queue.iter_mut() {
+            for frame in packet.frames.iter_mut() {
+                let _ = frame.keep.get_or_insert(true);
+            }
+        }
+
+        self.drain_decided()
+    }
+}
+
+/// Returns true if the frame can use the motion vectors and the segmentation map of the
+/// previous frame.
+fn uses_previous_state(frame: &Frame, previous: FrameState, shown_later: bool) -> bool {
+    frame.frame_type() == FrameType::NonKeyFrame
+        && !frame.intra_only()
+        && !frame.error_resilient_mode()
+        && frame.width() == previous.width
+        && frame.height() == previous.height
+        && (previous.shown || shown_later)
+        && !previous.intra_only
+}
+
+/// The reference slots a frame reads from.
+fn referenced_slots(frame: &Frame) -> u8 {
+    if let Some(index) = frame.frame_to_show_map_idx() {
+        return 1 << index;
+    }
+
+    if frame.frame_type() == FrameType::KeyFrame || frame.intra_only() {
+        return 0;
+    }
+
+    frame
+        .ref_frame_indices()
+        .iter()
+        .fold(0, |slots, index| slots | (1 << index))
+}
+
+fn refreshed_slots(frame: &Frame) -> u8 {
+    if frame.show_existing_frame() {
+        0
+    } else {
+        frame.refresh_frame_flags()
+    }
+}
+
+/// Returns true if the frame resets all four probability contexts to the defaults.
+fn resets_contexts(frame: &Frame) -> bool {
+    frame.frame_type() == FrameType::KeyFrame
+        || frame.error_resilient_mode()
+        || (frame.intra_only() && frame.reset_frame_context() == ResetFrameContext::FullReset)
+}
+
+/// The probability contexts a frame loads its probabilities from.
+fn loaded_contexts(frame: &Frame) -> u8 {
+    // A single reset of an intra-only frame isn't considered, since the reset context isn't
+    // known after parsing.
+    if frame.show_existing_frame() || resets_contexts(frame) {
+        0
+    } else {
+        1 << frame.frame_context_idx()
+    }
+}
+
+/// The probability contexts a frame overwrites.
+fn saved_contexts(frame: &Frame) -> u8 {
+    if frame.show_existing_frame() {
+        return 0;
+    }
+
+    let mut contexts = if resets_contexts(frame) { 0x0F } else { 0 };
+    if frame.refresh_frame_context() {
+        contexts |= 1 << frame.frame_context_idx();
+    }
+    contexts
+}
+
+/// Builds the packet out of the kept frames.
+fn assemble(packet: PendingPacket) -> Result<Option<Packet>> {
+    let frames: Vec<PendingFrame> = packet
+        .frames
+        .into_iter()
+        .filter(|frame| frame.keep == Some(true))
+        .collect();
+
+    let hidden = !frames.iter().any(|frame| frame.shown);
+    let data = match frames.len() {
+        0 => return Ok(None),
+        1 => frames.into_iter().flat_map(|frame| frame.data).collect(),
+        _ => {
+            let data: Vec<&[u8]> = frames.iter().map(|frame| frame.data.as_slice()).collect();
+            write_super_frame(&data)?
+        }
+    };
+
+    Ok(Some(Packet {
+        timestamp: packet.timestamp,
+        data,
+        hidden,
+    }))
+}
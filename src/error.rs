@@ -2,6 +2,8 @@
 
 use std::error::Error;
 
+use crate::Limit;
+
 /// Errors that can occur when parsing VP9 frames.
 #[derive(Debug)]
 pub enum Vp9ParserError {
@@ -25,6 +27,16 @@ pub enum Vp9ParserError {
     InvalidMetadata,
     /// Invalid frame_size byte size.
     InvalidFrameSizeByteSize(usize),
+    /// The super frame index doesn't match the packet.
+    InvalidSuperFrameIndex,
+    /// The header sizes exceed the size of the frame.
+    InvalidHeaderSize,
+    /// The packet already ends with a super frame index.
+    UnexpectedSuperFrameIndex,
+    /// A limit of the `Limits` was exceeded.
+    LimitExceeded(Limit),
+    /// A header value can't be represented in the bitstream.
+    InvalidHeaderValue(&'static str),
 }
 
 impl std::fmt::Display for Vp9ParserError {
@@ -60,6 +72,21 @@ impl std::fmt::Display for Vp9ParserError {
             Vp9ParserError::InvalidFrameSizeByteSize(size) => {
                 write!(f, "invalid frame_size byte size: {}", size)
             }
+            Vp9ParserError::InvalidSuperFrameIndex => {
+                write!(f, "invalid super frame index")
+            }
+            Vp9ParserError::InvalidHeaderSize => {
+                write!(f, "invalid header size")
+            }
+            Vp9ParserError::UnexpectedSuperFrameIndex => {
+                write!(f, "unexpected super frame index")
+            }
+            Vp9ParserError::LimitExceeded(limit) => {
+                write!(f, "limit exceeded: {}", limit)
+            }
+            Vp9ParserError::InvalidHeaderValue(name) => {
+                write!(f, "invalid header value: {}", name)
+            }
         }
     }
 }
@@ -82,6 +109,12 @@ impl From<std::num::TryFromIntError> for Vp9ParserError {
     }
 }
 
+impl From<Limit> for Vp9ParserError {
+    fn from(limit: Limit) -> Vp9ParserError {
+        Vp9ParserError::LimitExceeded(limit)
+    }
+}
+
 impl From<bitreader::BitReaderError> for Vp9ParserError {
     fn from(err: bitreader::BitReaderError) -> Vp9ParserError {
         Vp9ParserError::BitReaderError(err)
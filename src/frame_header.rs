@@ -0,0 +1,280 @@
+//! The parsed header of a frame, independent of the frame data.
+
+use crate::{
+    ColorDepth, ColorDescription, ColorRange, ColorSpace, FrameType, InterpolationFilter, Profile,
+    ResetFrameContext, Subsampling,
+};
+
+/// The header of a VP9 frame.
+///
+/// A plain value that doesn't borrow the packet, so headers can be stored without keeping
+/// the frame data alive.
+///
+/// A `show_existing_frame` header only codes the frame to show. Of its fields, `profile`,
+/// `show_existing_frame`, `frame_to_show_map_idx` and `uncompressed_header_size` are read from
+/// the frame, `show_frame` is always true, and `refresh_frame_flags`, the loop filter level and
+/// the sizes of the compressed header and tile data are zero. All other fields keep the values
+/// of the previously decoded frame and don't describe the shown frame.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct FrameHeader {
+    /// The profile the frame is using.
+    pub profile: Profile,
+    /// Indicates that the frame indexed by `frame_to_show_map_idx` is to be displayed.
+    /// The frame contains no actual frame data.
+    pub show_existing_frame: bool,
+    /// Specifies the frame to be displayed. It is only available if `show_existing_frame` is true.
+    pub frame_to_show_map_idx: Option<u8>,
+    /// The frame type of the previous frame.
+    pub last_frame_type: FrameType,
+    /// The frame type of this frame.
+    pub frame_type: FrameType,
+    /// Indicates that the frame is to be displayed.
+    pub show_frame: bool,
+    /// Indicates that error resilient mode is enabled.
+    ///
+    /// Error resilient mode allows the syntax of a frame to be decoded
+    /// independently of previous frames.
+    pub error_resilient_mode: bool,
+    /// Indicates that a frame is an `intra-only` frame.
+    ///
+    /// A key frame is different to an `intra-only` frame even though both only use
+    /// intra prediction. The difference is that a key frame fully resets the decoding process.
+    pub intra_only: bool,
+    /// Specifies whether the frame context should be reset to default values.
+    pub reset_frame_context: ResetFrameContext,
+    /// The indices of the used reference frames.
+    pub ref_frame_indices: [u8; 3],
+    /// Specifies the intended direction of the motion vector in time for each reference frame.
+    pub ref_frame_sign_bias: [bool; 4],
+    /// Specifies the precision of the motion vectors.
+    ///
+    /// False = quarter precision, True = eighth precision.
+    pub allow_high_precision_mv: bool,
+    /// Specifies that the probabilities computed for this frame
+    /// should be stored for reference by future frames.
+    pub refresh_frame_context: bool,
+    /// Contains a bitmask that specifies which reference frame slots
+    /// will be updated with the current frame after it is decoded.
+    ///
+    /// First bit = first frame (1). Last bit = last frame (8).
+    pub refresh_frame_flags: u8,
+    /// Specifies if parallel decoding mode is activated.
+    pub frame_parallel_decoding_mode: bool,
+    /// Specifies which frame context to use.
+    pub frame_context_idx: u8,
+    /// The type of filter used in inter prediction.
+    pub interpolation_filter: InterpolationFilter,
+    /// The size of the uncompressed header.
+    pub uncompressed_header_size: usize,
+    /// The size of the compressed header.
+    pub compressed_header_size: usize,
+    /// The size of the tile data.
+    pub tile_size: usize,
+    /// The color config of the frame.
+    pub color_config: ColorConfig,
+    /// The size of the frame.
+    pub frame_size: FrameSize,
+    /// The loop filter parameters.
+    pub loop_filter: LoopFilterParams,
+    /// The quantization parameters.
+    pub quantization: QuantizationParams,
+    /// The segmentation parameters.
+    pub segmentation: SegmentationParams,
+    /// The tile info.
+    pub tile_info: TileInfo,
+}
+
+/// The color config of a frame.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ColorConfig {
+    /// The color depth of the frame.
+    pub color_depth: ColorDepth,
+    /// The color space of the frame.
+    pub color_space: ColorSpace,
+    /// The color range of the frame.
+    pub color_range: ColorRange,
+    /// Indicates if sub sampling is used along the x axis.
+    pub subsampling_x: bool,
+    /// Indicates if sub sampling is used along the y axis.
+    pub subsampling_y: bool,
+}
+
+/// The frame size and render size of a frame.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct FrameSize {
+    /// The width of the frame.
+    pub width: u16,
+    /// The height of the frame.
+    pub height: u16,
+    /// The index into `ref_frame_indices` of the reference frame the frame size was taken from.
+    /// `None` if the frame size was coded explicitly.
+    pub found_ref: Option<u8>,
+    /// Indicates that the render size was coded explicitly.
+    pub render_and_frame_size_different: bool,
+    /// A hint for the application for the desired width to render.
+    pub render_width: u16,
+    /// A hint for the application for the desired height to render.
+    pub render_height: u16,
+    /// A variable holding the vertical location of the block in units of 8x8 pixels.
+    pub mi_cols: u16,
+    /// A variable holding the horizontal location of the block in units of 8x8 pixels.
+    pub mi_rows: u16,
+}
+
+/// The loop filter parameters of a frame.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct LoopFilterParams {
+    /// The loop filter strength.
+    pub loop_filter_level: u8,
+    /// The loop filter sharpness.
+    pub loop_filter_sharpness: u8,
+    /// Indicates that the filter level depends on the mode and reference frame
+    /// used to predict a block.
+    pub loop_filter_delta_enabled: bool,
+    /// Indicates that the bitstream contains updates of the loop filter deltas.
+    pub loop_filter_delta_update: bool,
+    /// Indicates that the the bitstream contains the syntax element loop_filter_ref_delta.
+    pub update_ref_delta: bool,
+    /// Indicates which of the loop_filter_ref_deltas are contained in the bitstream.
+    pub ref_deltas_updated: [bool; 4],
+    /// Contains the adjustment needed for the filter level based on the chosen reference frame.
+    pub loop_filter_ref_deltas: [i8; 4],
+    /// Indicates that the the bitstream contains the syntax element loop_filter_mode_deltas.
+    pub update_mode_delta: bool,
+    /// Indicates which of the loop_filter_mode_deltas are contained in the bitstream.
+    pub mode_deltas_updated: [bool; 2],
+    /// Contains the adjustment needed for the filter level based on the chosen mode.
+    pub loop_filter_mode_deltas: [i8; 2],
+}
+
+/// The quantization parameters of a frame.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct QuantizationParams {
+    /// The base frame qindex. This is used for Y AC coefficients and as the base value
+    /// for the other quantizers.
+    pub base_q_idx: i32,
+    /// The Y DC quantizer relative to base_q_idx.
+    pub delta_q_y_dc: i32,
+    /// The UV DC quantizer relative to base_q_idx.
+    pub delta_q_uv_dc: i32,
+    /// The UV AC quantizer relative to base_q_idx.
+    pub delta_q_uv_ac: i32,
+    /// Indicates that the frame is coded using a special 4x4 transform designed
+    /// for encoding frames that are bit-identical with the original frames.
+    pub lossless: bool,
+}
+
+/// The segmentation parameters of a frame.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct SegmentationParams {
+    /// Specifies that this frame makes use of the segmentation tool.
+    pub segmentation_enabled: bool,
+    /// Specifies that the segmentation map should be updated during the decoding of this frame.
+    pub segmentation_update_map: bool,
+    /// The probability values to be used when decoding segment_id.
+    pub segment_tree_probs: [u8; 7],
+    /// The probability values to be used when decoding seg_id_predicted.
+    pub segment_pred_probs: [u8; 3],
+    /// Indicates that the updates to the segmentation map are coded
+    /// relative to the existing segmentation map.
+    pub segmentation_temporal_update: bool,
+    /// Indicates that new parameters are about to be specified for each segment.
+    pub segmentation_update_data: bool,
+    /// Indicates that the segmentation parameters represent the actual values to be used,
+    /// otherwise the segmentation parameters represent adjustments relative to the standard values.
+    pub segmentation_abs_or_delta_update: bool,
+    /// Indicates that the corresponding feature is used in a segment.
+    pub segment_feature_enabled: [[bool; 4]; 8],
+    /// Specifies the values of the active features of a segment.
+    pub segment_feature_data: [[i16; 4]; 8],
+}
+
+/// The tile info of a frame.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct TileInfo {
+    /// The base 2 logarithm of the height of each tile (where the height is measured in units
+    /// of 8x8 blocks)
+    pub tile_rows_log2: u8,
+    /// The base 2 logarithm of the width of each tile (where the width is measured in units
+    /// of 8x8 blocks)
+    pub tile_cols_log2: u8,
+}
+
+impl Default for FrameHeader {
+    fn default() -> Self {
+        Self {
+            profile: Profile::Profile0,
+            show_existing_frame: false,
+            frame_to_show_map_idx: None,
+            last_frame_type: FrameType::NonKeyFrame,
+            frame_type: FrameType::NonKeyFrame,
+            show_frame: false,
+            error_resilient_mode: false,
+            intra_only: false,
+            reset_frame_context: ResetFrameContext::No0,
+            ref_frame_indices: [0u8; 3],
+            ref_frame_sign_bias: [false; 4],
+            allow_high_precision_mv: false,
+            refresh_frame_context: false,
+            refresh_frame_flags: 0,
+            frame_parallel_decoding_mode: true,
+            frame_context_idx: 0,
+            interpolation_filter: InterpolationFilter::Eighttap,
+            uncompressed_header_size: 0,
+            compressed_header_size: 0,
+            tile_size: 0,
+            color_config: ColorConfig::default(),
+            frame_size: FrameSize::default(),
+            loop_filter: LoopFilterParams::default(),
+            quantization: QuantizationParams::default(),
+            segmentation: SegmentationParams::default(),
+            tile_info: TileInfo::default(),
+        }
+    }
+}
+
+impl ColorConfig {
+    /// The subsampling the frame is using.
+    pub fn subsampling(&self) -> Subsampling {
+        match (self.subsampling_x, self.subsampling_y) {
+            (false, false) => Subsampling::Yuv444,
+            (false, true) => Subsampling::Yuv440,
+            (true, false) => Subsampling::Yuv422,
+            (true, true) => Subsampling::Yuv420,
+        }
+    }
+
+    /// The color space and color range of the frame as ITU-T H.273 code points.
+    pub fn color_description(&self) -> ColorDescription {
+        (self.color_space, self.color_range, self.color_depth).into()
+    }
+}
+
+impl Default for ColorConfig {
+    fn default() -> Self {
+        Self {
+            color_depth: ColorDepth::Depth8,
+            color_space: ColorSpace::Unknown,
+            color_range: ColorRange::StudioSwing,
+            subsampling_x: true,
+            subsampling_y: true,
+        }
+    }
+}
+
+impl Default for LoopFilterParams {
+    fn default() -> Self {
+        Self {
+            loop_filter_level: 0,
+            loop_filter_sharpness: 0,
+            loop_filter_delta_enabled: false,
+            loop_filter_delta_update: false,
+            update_ref_delta: false,
+            ref_deltas_updated: [false; 4],
+            loop_filter_ref_deltas: [1, 0, -1, -1],
+            update_mode_delta: false,
+            mode_deltas_updated: [false; 2],
+            loop_filter_mode_deltas: [0, 0],
+        }
+    }
+}
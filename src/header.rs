@@ -0,0 +1,580 @@
+//! Serialization of the uncompressed header.
+
+use crate::{
+    calc_max_log2_tile_cols, calc_mi_units, calc_min_log2_tile_cols, ColorDepth, ColorRange,
+    ColorSpace, Frame, FrameHeader, FrameType, InterpolationFilter, Profile, ResetFrameContext,
+    Result, Vp9ParserError, LAST_FRAME, MAX_SEGMENTS, SEG_LVL_ALT_L, SEG_LVL_ALT_Q,
+    SEG_LVL_REF_FRAME, SEG_LVL_SKIP,
+};
+
+/// The syntax elements of the uncompressed header of a frame.
+///
+/// Can be created from a parsed [`Frame`] or by hand and serialized back into the bitstream
+/// with [`UncompressedHeader::to_bytes`]. Values that are not coded in the bitstream for the
+/// given frame, like the color config of inter frames, are ignored when writing.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UncompressedHeader {
+    /// The profile of the frame.
+    pub profile: Profile,
+    /// Indicates that the frame indexed by `frame_to_show_map_idx` is to be displayed.
+    pub show_existing_frame: bool,
+    /// Specifies the frame to be displayed if `show_existing_frame` is set.
+    pub frame_to_show_map_idx: u8,
+    /// The frame type.
+    pub frame_type: FrameType,
+    /// Indicates that the frame is to be displayed.
+    pub show_frame: bool,
+    /// Indicates that error resilient mode is enabled.
+    pub error_resilient_mode: bool,
+    /// Indicates that a frame is an `intra-only` frame.
+    pub intra_only: bool,
+    /// Specifies whether the frame context should be reset to default values.
+    pub reset_frame_context: ResetFrameContext,
+    /// The reference frame slots that are updated with this frame.
+    pub refresh_frame_flags: u8,
+    /// The indices of the used reference frames.
+    pub ref_frame_indices: [u8; 3],
+    /// The sign bias of the reference frames, indexed by reference frame type.
+    pub ref_frame_sign_bias: [bool; 4],
+    /// Specifies the precision of the motion vectors.
+    pub allow_high_precision_mv: bool,
+    /// The type of filter used in inter prediction.
+    pub interpolation_filter: InterpolationFilter,
+    /// The color depth.
+    pub color_depth: ColorDepth,
+    /// The color space.
+    pub color_space: ColorSpace,
+    /// The color range.
+    pub color_range: ColorRange,
+    /// Indicates if sub sampling is used along the x axis.
+    pub subsampling_x: bool,
+    /// Indicates if sub sampling is used along the y axis.
+    pub subsampling_y: bool,
+    /// The width of the frame.
+    pub width: u16,
+    /// The height of the frame.
+    pub height: u16,
+    /// The index into `ref_frame_indices` of the reference frame the frame size is taken from.
+    /// Only used by inter frames, the frame size is coded explicitly if it's `None`.
+    pub found_ref: Option<u8>,
+    /// Indicates that the render size is coded explicitly.
+    pub render_and_frame_size_different: bool,
+    /// The render width.
+    pub render_width: u16,
+    /// The render height.
+    pub render_height: u16,
+    /// Specifies that the probabilities of this frame are stored for future frames.
+    pub refresh_frame_context: bool,
+    /// Specifies if parallel decoding mode is activated.
+    pub frame_parallel_decoding_mode: bool,
+    /// Specifies which frame context to use.
+    pub frame_context_idx: u8,
+    /// The loop filter strength.
+    pub loop_filter_level: u8,
+    /// The loop filter sharpness.
+    pub loop_filter_sharpness: u8,
+    /// Indicates that the filter level depends on the mode and reference frame.
+    pub loop_filter_delta_enabled: bool,
+    /// Indicates that the loop filter deltas are updated.
+    pub loop_filter_delta_update: bool,
+    /// Indicates which of the `loop_filter_ref_deltas` are coded.
+    pub ref_deltas_updated: [bool; 4],
+    /// The loop filter adjustments based on the reference frame.
+    pub loop_filter_ref_deltas: [i8; 4],
+    /// Indicates which of the `loop_filter_mode_deltas` are coded.
+    pub mode_deltas_updated: [bool; 2],
+    /// The loop filter adjustments based on the mode.
+    pub loop_filter_mode_deltas: [i8; 2],
+    /// The base frame qindex.
+    pub base_q_idx: i32,
+    /// The Y DC quantizer relative to base_q_idx.
+    pub delta_q_y_dc: i32,
+    /// The UV DC quantizer relative to base_q_idx.
+    pub delta_q_uv_dc: i32,
+    /// The UV AC quantizer relative to base_q_idx.
+    pub delta_q_uv_ac: i32,
+    /// Specifies that the frame makes use of the segmentation tool.
+    pub segmentation_enabled: bool,
+    /// Specifies that the segmentation map is updated.
+    pub segmentation_update_map: bool,
+    /// The probabilities used when decoding segment_id. 255 is not coded.
+    pub segment_tree_probs: [u8; 7],
+    /// Indicates that the segmentation map is coded relative to the existing map.
+    pub segmentation_temporal_update: bool,
+    /// The probabilities used when decoding seg_id_predicted. 255 is not coded.
+    pub segment_pred_probs: [u8; 3],
+    /// Indicates that new parameters are specified for each segment.
+    pub segmentation_update_data: bool,
+    /// Indicates that the segmentation parameters are absolute values.
+    pub segmentation_abs_or_delta_update: bool,
+    /// Indicates that the corresponding feature is used in a segment.
+    pub segment_feature_enabled: [[bool; 4]; 8],
+    /// The values of the active features of a segment.
+    pub segment_feature_data: [[i16; 4]; 8],
+    /// The base 2 logarithm of the number of tile columns.
+    pub tile_cols_log2: u8,
+    /// The base 2 logarithm of the number of tile rows.
+    pub tile_rows_log2: u8,
+    /// The size of the compressed header in bytes.
+    pub header_size_in_bytes: usize,
+}
+
+impl From<&FrameHeader> for UncompressedHeader {
+    fn from(header: &FrameHeader) -> Self {
+        Self {
+            profile: header.profile,
+            show_existing_frame: header.show_existing_frame,
+            frame_to_show_map_idx: header.frame_to_show_map_idx.unwrap_or_default(),
+            frame_type: header.frame_type,
+            show_frame: header.show_frame,
+            error_resilient_mode: header.error_resilient_mode,
+            intra_only: header.intra_only,
+            reset_frame_context: header.reset_frame_context,
+            refresh_frame_flags: header.refresh_frame_flags,
+            ref_frame_indices: header.ref_frame_indices,
+            ref_frame_sign_bias: header.ref_frame_sign_bias,
+            allow_high_precision_mv: header.allow_high_precision_mv,
+            interpolation_filter: header.interpolation_filter,
+            color_depth: header.color_config.color_depth,
+            color_space: header.color_config.color_space,
+            color_range: header.color_config.color_range,
+            subsampling_x: header.color_config.subsampling_x,
+            subsampling_y: header.color_config.subsampling_y,
+            width: header.frame_size.width,
+            height: header.frame_size.height,
+            found_ref: header.frame_size.found_ref,
+            render_and_frame_size_different: header.frame_size.render_and_frame_size_different,
+            render_width: header.frame_size.render_width,
+            render_height: header.frame_size.render_height,
+            refresh_frame_context: header.refresh_frame_context,
+            frame_parallel_decoding_mode: header.frame_parallel_decoding_mode,
+            frame_context_idx: header.frame_context_idx,
+            loop_filter_level: header.loop_filter.loop_filter_level,
+            loop_filter_sharpness: header.loop_filter.loop_filter_sharpness,
+            loop_filter_delta_enabled: header.loop_filter.loop_filter_delta_enabled,
+            loop_filter_delta_update: header.loop_filter.loop_filter_delta_update,
+            ref_deltas_updated: header.loop_filter.ref_deltas_updated,
+            loop_filter_ref_deltas: header.loop_filter.loop_filter_ref_deltas,
+            mode_deltas_updated: header.loop_filter.mode_deltas_updated,
+            loop_filter_mode_deltas: header.loop_filter.loop_filter_mode_deltas,
+            base_q_idx: header.quantization.base_q_idx,
+            delta_q_y_dc: header.quantization.delta_q_y_dc,
+            delta_q_uv_dc: header.quantization.delta_q_uv_dc,
+            delta_q_uv_ac: header.quantization.delta_q_uv_ac,
+            segmentation_enabled: header.segmentation.segmentation_enabled,
+            segmentation_update_map: header.segmentation.segmentation_update_map,
+            segment_tree_probs: header.segmentation.segment_tree_probs,
+            segmentation_temporal_update: header.segmentation.segmentation_temporal_update,
+            segment_pred_probs: header.segmentation.segment_pred_probs,
+            segmentation_update_data: header.segmentation.segmentation_update_data,
+            segmentation_abs_or_delta_update: header.segmentation.segmentation_abs_or_delta_update,
+            segment_feature_enabled: header.segmentation.segment_feature_enabled,
+            segment_feature_data: header.segmentation.segment_feature_data,
+            tile_cols_log2: header.tile_info.tile_cols_log2,
+            tile_rows_log2: header.tile_info.tile_rows_log2,
+            header_size_in_bytes: header.compressed_header_size,
+        }
+    }
+}
+
+impl<'a> From<&Frame<'a>> for UncompressedHeader {
+    fn from(frame: &Frame<'a>) -> Self {
+        Self::from(frame.header())
+    }
+}
+
+impl UncompressedHeader {
+    /// Serializes the header including the trailing bits.
+    ///
+    /// Returns `Vp9ParserError::InvalidHeaderValue` with the name of the syntax element
+    /// if a value can't be represented in the bitstream.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut bw = BitWriter::default();
+
+        bw.write(2, 2, "frame_marker")?;
+        let profile = u8::from(self.profile);
+        if profile > 3 {
+            return Err(Vp9ParserError::InvalidHeaderValue("profile"));
+        }
+        bw.write(profile & 1, 1, "profile_low_bit")?;
+        bw.write(profile >> 1, 1, "profile_high_bit")?;
+        if self.profile == Profile::Profile3 {
+            bw.write(0, 1, "reserved_zero")?;
+        }
+
+        bw.write_bool(self.show_existing_frame);
+        if self.show_existing_frame {
+            bw.write(self.frame_to_show_map_idx, 3, "frame_to_show_map_idx")?;
+            return Ok(bw.finish());
+        }
+
+        bw.write_bool(self.frame_type == FrameType::NonKeyFrame);
+        bw.write_bool(self.show_frame);
+        bw.write_bool(self.error_resilient_mode);
+
+        if self.frame_type == FrameType::KeyFrame {
+            self.frame_sync_code(&mut bw)?;
+            self.color_config(&mut bw)?;
+            self.frame_size(&mut bw)?;
+            self.render_size(&mut bw)?;
+        } else {
+            if !self.show_frame {
+                bw.write_bool(self.intra_only);
+            } else if self.intra_only {
+                return Err(Vp9ParserError::InvalidHeaderValue("intra_only"));
+            }
+
+            if !self.error_resilient_mode {
+                bw.write(u8::from(self.reset_frame_context), 2, "reset_frame_context")?;
+            }
+
+            if self.intra_only {
+                self.frame_sync_code(&mut bw)?;
+                if self.profile > Profile::Profile0 {
+                    self.color_config(&mut bw)?;
+                }
+                bw.write(self.refresh_frame_flags, 8, "refresh_frame_flags")?;
+                self.frame_size(&mut bw)?;
+                self.render_size(&mut bw)?;
+            } else {
+                bw.write(self.refresh_frame_flags, 8, "refresh_frame_flags")?;
+                for i in 0..3 {
+                    bw.write(self.ref_frame_indices[i], 3, "ref_frame_idx")?;
+                    bw.write_bool(self.ref_frame_sign_bias[LAST_FRAME + i]);
+                }
+                self.frame_size_with_refs(&mut bw)?;
+                bw.write_bool(self.allow_high_precision_mv);
+                self.interpolation_filter(&mut bw)?;
+            }
+        }
+
+        if !self.error_resilient_mode {
+            bw.write_bool(self.refresh_frame_context);
+            bw.write_bool(self.frame_parallel_decoding_mode);
+        }
+        bw.write(self.frame_context_idx, 2, "frame_context_idx")?;
+
+        self.loop_filter_params(&mut bw)?;
+        self.quantization_params(&mut bw)?;
+        self.segmentation_params(&mut bw)?;
+        self.tile_info(&mut bw)?;
+
+        bw.write(
+            i64::try_from(self.header_size_in_bytes)
+                .map_err(|_| Vp9ParserError::InvalidHeaderValue("header_size_in_bytes"))?,
+            16,
+            "header_size_in_bytes",
+        )?;
+
+        Ok(bw.finish())
+    }
+
+    fn frame_sync_code(&self, bw: &mut BitWriter) -> Result<()> {
+        bw.write(0x49, 8, "frame_sync_byte_0")?;
+        bw.write(0x83, 8, "frame_sync_byte_1")?;
+        bw.write(0x42, 8, "frame_sync_byte_2")
+    }
+
+    fn color_config(&self, bw: &mut BitWriter) -> Result<()> {
+        if self.profile >= Profile::Profile2 {
+            match self.color_depth {
+                ColorDepth::Depth10 => bw.write_bool(false),
+                ColorDepth::Depth12 => bw.write_bool(true),
+                _ => return Err(Vp9ParserError::InvalidHeaderValue("color_depth")),
+            }
+        } else if self.color_depth != ColorDepth::Depth8 {
+            return Err(Vp9ParserError::InvalidHeaderValue("color_depth"));
+        }
+
+        bw.write(u8::from(self.color_space), 3, "color_space")?;
+
+        let has_subsampling_bits =
+            self.profile == Profile::Profile1 || self.profile == Profile::Profile3;
+        if self.color_space == ColorSpace::Rgb {
+            if has_subsampling_bits {
+                bw.write(0, 1, "reserved_zero")?;
+            }
+        } else {
+            bw.write_bool(self.color_range == ColorRange::FullSwing);
+            if has_subsampling_bits {
+                bw.write_bool(self.subsampling_x);
+                bw.write_bool(self.subsampling_y);
+                bw.write(0, 1, "reserved_zero")?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn frame_size(&self, bw: &mut BitWriter) -> Result<()> {
+        bw.write_minus_1(self.width, 16, "frame_width_minus_1")?;
+        bw.write_minus_1(self.height, 16, "frame_height_minus_1")
+    }
+
+    fn render_size(&self, bw: &mut BitWriter) -> Result<()> {
+        bw.write_bool(self.render_and_frame_size_different);
+        if self.render_and_frame_size_different {
+            bw.write_minus_1(self.render_width, 16, "render_width_minus_1")?;
+            bw.write_minus_1(self.render_height, 16, "render_height_minus_1")?;
+        }
+
+        Ok(())
+    }
+
+    fn frame_size_with_refs(&self, bw: &mut BitWriter) -> Result<()> {
+        match self.found_ref {
+            Some(found_ref) if found_ref < 3 => {
+                for i in 0..=found_ref {
+                    bw.write_bool(i == found_ref);
+                }
+            }
+            Some(_) => return Err(Vp9ParserError::InvalidHeaderValue("found_ref")),
+            None => {
+                for _ in 0..3 {
+                    bw.write_bool(false);
+                }
+                self.frame_size(bw)?;
+            }
+        }
+
+        self.render_size(bw)
+    }
+
+    fn interpolation_filter(&self, bw: &mut BitWriter) -> Result<()> {
+        let raw_interpolation_filter = match self.interpolation_filter {
+            InterpolationFilter::Switchable => {
+                bw.write_bool(true);
+                return Ok(());
+            }
+            InterpolationFilter::EighttapSmooth => 0,
+            InterpolationFilter::Eighttap => 1,
+            InterpolationFilter::EighttapSharp => 2,
+            InterpolationFilter::Bilinear => 3,
+            InterpolationFilter::Unknown => {
+                return Err(Vp9ParserError::InvalidHeaderValue("interpolation_filter"))
+            }
+        };
+
+        bw.write_bool(false);
+        bw.write(raw_interpolation_filter, 2, "raw_interpolation_filter")
+    }
+
+    fn loop_filter_params(&self, bw: &mut BitWriter) -> Result<()> {
+        bw.write(self.loop_filter_level, 6, "loop_filter_level")?;
+        bw.write(self.loop_filter_sharpness, 3, "loop_filter_sharpness")?;
+        bw.write_bool(self.loop_filter_delta_enabled);
+
+        if self.loop_filter_delta_enabled {
+            bw.write_bool(self.loop_filter_delta_update);
+            if self.loop_filter_delta_update {
+                for (delta, updated) in self
+                    .loop_filter_ref_deltas
+                    .iter()
+                    .zip(self.ref_deltas_updated.iter())
+                {
+                    bw.write_bool(*updated);
+                    if *updated {
+                        bw.write_signed(*delta, 6, "loop_filter_ref_deltas")?;
+                    }
+                }
+
+                for (mode, updated) in self
+                    .loop_filter_mode_deltas
+                    .iter()
+                    .zip(self.mode_deltas_updated.iter())
+                {
+                    bw.write_bool(*updated);
+                    if *updated {
+                        bw.write_signed(*mode, 6, "loop_filter_mode_deltas")?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn quantization_params(&self, bw: &mut BitWriter) -> Result<()> {
+        bw.write(self.base_q_idx, 8, "base_q_idx")?;
+        Self::write_delta_q(bw, self.delta_q_y_dc, "delta_q_y_dc")?;
+        Self::write_delta_q(bw, self.delta_q_uv_dc, "delta_q_uv_dc")?;
+        Self::write_delta_q(bw, self.delta_q_uv_ac, "delta_q_uv_ac")
+    }
+
+    fn write_delta_q(bw: &mut BitWriter, delta_q: i32, name: &'static str) -> Result<()> {
+        bw.write_bool(delta_q != 0);
+        if delta_q != 0 {
+            bw.write_signed(delta_q, 4, name)?;
+        }
+
+        Ok(())
+    }
+
+    fn segmentation_params(&self, bw: &mut BitWriter) -> Result<()> {
+        bw.write_bool(self.segmentation_enabled);
+        if !self.segmentation_enabled {
+            return Ok(());
+        }
+
+        bw.write_bool(self.segmentation_update_map);
+        if self.segmentation_update_map {
+            for prob in self.segment_tree_probs.iter() {
+                Self::write_prob(bw, *prob);
+            }
+
+            bw.write_bool(self.segmentation_temporal_update);
+            if self.segmentation_temporal_update {
+                for prob in self.segment_pred_probs.iter() {
+                    Self::write_prob(bw, *prob);
+                }
+            }
+        }
+
+        bw.write_bool(self.segmentation_update_data);
+        if self.segmentation_update_data {
+            bw.write_bool(self.segmentation_abs_or_delta_update);
+            for i in 0..MAX_SEGMENTS {
+                let enabled = &self.segment_feature_enabled[i];
+                let data = &self.segment_feature_data[i];
+
+                bw.write_bool(enabled[SEG_LVL_ALT_Q]);
+                if enabled[SEG_LVL_ALT_Q] {
+                    bw.write_signed(data[SEG_LVL_ALT_Q], 8, "segment_feature_data")?;
+                }
+                bw.write_bool(enabled[SEG_LVL_ALT_L]);
+                if enabled[SEG_LVL_ALT_L] {
+                    bw.write_signed(data[SEG_LVL_ALT_L], 6, "segment_feature_data")?;
+                }
+                bw.write_bool(enabled[SEG_LVL_REF_FRAME]);
+                if enabled[SEG_LVL_REF_FRAME] {
+                    bw.write(data[SEG_LVL_REF_FRAME], 2, "segment_feature_data")?;
+                }
+                bw.write_bool(enabled[SEG_LVL_SKIP]);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_prob(bw: &mut BitWriter, prob: u8) {
+        bw.write_bool(prob != 255);
+        if prob != 255 {
+            bw.write_byte(prob);
+        }
+    }
+
+    fn tile_info(&self, bw: &mut BitWriter) -> Result<()> {
+        let mi_cols = calc_mi_units(self.width);
+        let min_log2_tile_cols = calc_min_log2_tile_cols(mi_cols);
+        let max_log2_tile_cols = calc_max_log2_tile_cols(mi_cols);
+        if self.tile_cols_log2 < min_log2_tile_cols || self.tile_cols_log2 > max_log2_tile_cols {
+            return Err(Vp9ParserError::InvalidHeaderValue("tile_cols_log2"));
+        }
+
+        for _ in min_log2_tile_cols..self.tile_cols_log2 {
+            bw.write_bool(true);
+        }
+        if self.tile_cols_log2 < max_log2_tile_cols {
+            bw.write_bool(false);
+        }
+
+        match self.tile_rows_log2 {
+            0 => bw.write_bool(false),
+            1 => {
+                bw.write_bool(true);
+                bw.write_bool(false);
+            }
+            2 => {
+                bw.write_bool(true);
+                bw.write_bool(true);
+            }
+            _ => return Err(Vp9ParserError::InvalidHeaderValue("tile_rows_log2")),
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes MSB first, like `BitReader` reads.
+#[derive(Default)]
+struct BitWriter {
+    data: Vec<u8>,
+    bit_position: u8,
+}
+
+impl BitWriter {
+    fn write_bool(&mut self, bit: bool) {
+        if self.bit_position == 0 {
+            self.data.push(0);
+        }
+        if bit {
+            if let Some(byte) = self.data.last_mut() {
+                *byte |= 0x80 >> self.bit_position;
+            }
+        }
+        self.bit_position = (self.bit_position + 1) % 8;
+    }
+
+    fn write_byte(&mut self, value: u8) {
+        for i in (0..8).rev() {
+            self.write_bool((value >> i) & 1 == 1);
+        }
+    }
+
+    /// Writes an unsigned value. Fails if the value doesn't fit into `bits`.
+    fn write(&mut self, value: impl Into<i64>, bits: u32, name: &'static str) -> Result<()> {
+        let value = value.into();
+        if value < 0 || value >> bits != 0 {
+            return Err(Vp9ParserError::InvalidHeaderValue(name));
+        }
+
+        for i in (0..bits).rev() {
+            self.write_bool((value >> i) & 1 == 1);
+        }
+
+        Ok(())
+    }
+
+    /// Writes `value - 1`, the way the frame and render sizes are coded.
+    fn write_minus_1(&mut self, value: u16, bits: u32, name: &'static str) -> Result<()> {
+        self.write(i64::from(value) - 1, bits, name)
+    }
+
+    /// Writes the magnitude followed by the sign bit.
+    fn write_signed(&mut self, value: impl Into<i64>, bits: u32, name: &'static str) -> Result<()> {
+        let value = value.into();
+        self.write(value.abs(), bits, name)?;
+        self.write_bool(value < 0);
+        Ok(())
+    }
+
+    /// Returns the data. The last byte is already padded with zero bits.
+    fn finish(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+    use crate::Vp9Parser;
+
+    #[test]
+    fn write_show_existing_frame() {
+        let data = [0x8B];
+        let mut parser = Vp9Parser::default();
+        let frame = parser.parse_packet(&data).next().unwrap().unwrap();
+        assert_eq!(frame.frame_to_show_map_idx(), Some(3));
+
+        let mut header = UncompressedHeader::from(&frame);
+        assert_eq!(header.to_bytes().unwrap(), data);
+
+        header.frame_to_show_map_idx = 8;
+        assert!(matches!(
+            header.to_bytes(),
+            Err(Vp9ParserError::InvalidHeaderValue("frame_to_show_map_idx"))
+        ));
+    }
+}
@@ -0,0 +1,198 @@
+//! IVF parsing over asynchronous readers.
+
+use std::io::ErrorKind;
+
+use futures::{
+    io::{AsyncRead, AsyncReadExt},
+    stream::{self, Stream},
+};
+
+use super::{
+    FourCc, Frame, IvfHeader, IvfOptions, ReadState, Result, Resync, ResyncStep, Timebase,
+    HEADER_SIZE,
+};
+
+/// An IVF that is read from an asynchronous reader.
+///
+/// Parses the header and frames the same way as `Ivf`, but doesn't support seeking.
+#[derive(Debug, Clone)]
+pub struct AsyncIvf<R> {
+    reader: R,
+    header: IvfHeader,
+    state: ReadState,
+}
+
+impl<R: AsyncRead + Unpin> AsyncIvf<R> {
+    /// Creates a new IVF using the given reader.
+    pub async fn new(reader: R) -> Result<Self> {
+        Self::with_options(reader, &IvfOptions::default()).await
+    }
+
+    /// Creates a new IVF using the given reader and options.
+    pub async fn with_options(mut reader: R, options: &IvfOptions) -> Result<Self> {
+        let mut d = vec![0u8; usize::from(HEADER_SIZE)];
+        reader.read_exact(&mut d).await?;
+        let header = IvfHeader::parse(&d, options)?;
+
+        if header.length > HEADER_SIZE {
+            let mut extension = vec![0u8; usize::from(header.length - HEADER_SIZE)];
+            reader.read_exact(&mut extension).await?;
+        }
+
+        Ok(Self {
+            reader,
+            header,
+            state: ReadState::new(options),
+        })
+    }
+
+    /// The codec of the frames.
+    pub fn four_cc(&self) -> FourCc {
+        self.header.four_cc
+    }
+
+    /// The version of the IVF.
+    pub fn version(&self) -> u16 {
+        self.header.version
+    }
+
+    /// The initial width of the video.
+    pub fn width(&self) -> u16 {
+        self.header.width
+    }
+
+    /// The initial height of the video.
+    pub fn height(&self) -> u16 {
+        self.header.height
+    }
+
+    /// The framerate of the video (frame_rate_rate * frame_rate_scale).
+    pub fn frame_rate_rate(&self) -> u32 {
+        self.header.frame_rate_rate
+    }
+
+    /// Divider of the seconds.
+    pub fn frame_rate_scale(&self) -> u32 {
+        self.header.frame_rate_scale
+    }
+
+    /// Number of frames stored inside the IVF. A frame can contain a frame or a super frame.
+    pub fn frame_count(&self) -> u32 {
+        self.header.frame_count
+    }
+
+    /// The timebase of the timestamps, `frame_rate_scale / frame_rate_rate` seconds.
+    pub fn timebase(&self) -> Timebase {
+        self.header.timebase()
+    }
+
+    /// Returns true if the file ended inside a frame. Only set in recovery mode.
+    pub fn is_truncated(&self) -> bool {
+        self.state.truncated
+    }
+
+    /// The number of bytes that were skipped to resynchronize in recovery mode.
+    pub fn skipped_bytes(&self) -> u64 {
+        self.state.skipped_bytes
+    }
+
+    /// Reads the next frame inside the IVF. Returns `None` if the end of the file has been reached.
+    pub async fn read_frame(&mut self) -> Result<Option<Frame>> {
+        let mut data = Vec::new();
+        let timestamp = self.read_frame_into(&mut data).await?;
+
+        Ok(timestamp.map(|timestamp| Frame {
+            timestamp,
+            packet: data,
+        }))
+    }
+
+    /// Reads the next frame into the given buffer and returns its timestamp. Returns `None`
+    /// if the end of the file has been reached.
+    pub async fn read_frame_into(&mut self, buffer: &mut Vec<u8>) -> Result<Option<u64>> {
+        buffer.clear();
+
+        let mut frame_header = [0u8; 12];
+        let read = read_full(&mut self.reader, &mut frame_header).await?;
+        if !self.state.frame_header_read(read)? {
+            return Ok(None);
+        }
+
+        let timestamp = if self.state.recover {
+            match self.recover_frame(frame_header, buffer).await? {
+                Some(timestamp) => timestamp,
+                None => return Ok(None),
+            }
+        } else {
+            let (size, timestamp) = self.state.parse_frame_header(&frame_header)?;
+            self.read_packet(size, buffer).await?;
+            timestamp
+        };
+
+        Ok(Some(self.state.next_timestamp(timestamp)))
+    }
+
+    /// Returns a stream of the remaining frames. The stream ends after the first error.
+    pub fn frames(&mut self) -> impl Stream<Item = Result<Frame>> + '_ {
+        stream::unfold(Some(self), |ivf| async move {
+            let ivf = ivf?;
+            match ivf.read_frame().await {
+                Ok(Some(frame)) => Some((Ok(frame), Some(ivf))),
+                Ok(None) => None,
+                Err(err) => Some((Err(err), None)),
+            }
+        })
+    }
+
+    /// Reads a packet of the given size into the buffer.
+    async fn read_packet(&mut self, size: usize, buffer: &mut Vec<u8>) -> Result<()> {
+        let read = (&mut self.reader)
+            .take(u64::try_from(size)?)
+            .read_to_end(buffer)
+            .await?;
+        self.state.packet_read(size, read)
+    }
+
+    /// Reads the frame of the given header, resynchronizing if the header is not plausible.
+    async fn recover_frame(
+        &mut self,
+        frame_header: [u8; 12],
+        buffer: &mut Vec<u8>,
+    ) -> Result<Option<u64>> {
+        let mut resync = Resync::new(frame_header);
+        loop {
+            match resync.step(&self.state, self.header.four_cc)? {
+                ResyncStep::NeedByte => {
+                    let mut byte = [0u8; 1];
+                    if read_full(&mut self.reader, &mut byte).await? == 0 {
+                        return resync.end_of_file(&mut self.state);
+                    }
+                    resync.push(byte[0]);
+                }
+                ResyncStep::Found { size, timestamp } => {
+                    let remaining = resync.found(&mut self.state, timestamp, buffer);
+                    self.read_packet(size.saturating_sub(remaining), buffer)
+                        .await?;
+                    return Ok(Some(timestamp));
+                }
+            }
+        }
+    }
+}
+
+/// Fills the buffer and returns the number of bytes read, like the synchronous `read_full`.
+async fn read_full<R: AsyncRead + Unpin>(
+    reader: &mut R,
+    buffer: &mut [u8],
+) -> std::io::Result<usize> {
+    let mut read = 0;
+    while read < buffer.len() {
+        match reader.read(&mut buffer[read..]).await {
+            Ok(0) => break,
+            Ok(n) => read += n,
+            Err(err) if err.kind() == ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(read)
+}
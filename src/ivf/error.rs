@@ -2,6 +2,8 @@
 
 use std::error::Error;
 
+use crate::Limit;
+
 /// Errors that can occur when parsing IVF containers.
 #[derive(Debug)]
 pub enum IvfError {
@@ -15,6 +17,14 @@ pub enum IvfError {
     InvalidHeader(String),
     /// Unexpected file ending.
     UnexpectedFileEnding,
+    /// The frame with the given number doesn't exist.
+    FrameNotFound(usize),
+    /// The operation is not supported for the codec.
+    UnsupportedFourCc(super::FourCc),
+    /// A limit of the `Limits` was exceeded.
+    LimitExceeded(Limit),
+    /// A `Vp9ParserError`.
+    Vp9ParserError(crate::Vp9ParserError),
 }
 
 impl std::fmt::Display for IvfError {
@@ -35,6 +45,18 @@ impl std::fmt::Display for IvfError {
             IvfError::UnexpectedFileEnding => {
                 write!(f, "unexpected file ending")
             }
+            IvfError::FrameNotFound(number) => {
+                write!(f, "frame not found: {}", number)
+            }
+            IvfError::UnsupportedFourCc(four_cc) => {
+                write!(f, "unsupported four_cc: {:?}", four_cc)
+            }
+            IvfError::LimitExceeded(limit) => {
+                write!(f, "limit exceeded: {}", limit)
+            }
+            IvfError::Vp9ParserError(err) => {
+                write!(f, "{}", err)
+            }
         }
     }
 }
@@ -57,12 +79,25 @@ impl From<std::num::TryFromIntError> for IvfError {
     }
 }
 
+impl From<Limit> for IvfError {
+    fn from(limit: Limit) -> IvfError {
+        IvfError::LimitExceeded(limit)
+    }
+}
+
+impl From<crate::Vp9ParserError> for IvfError {
+    fn from(err: crate::Vp9ParserError) -> IvfError {
+        IvfError::Vp9ParserError(err)
+    }
+}
+
 impl std::error::Error for IvfError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match *self {
             IvfError::IoError(ref e) => Some(e),
             IvfError::TryFromSliceError(ref e) => Some(e),
             IvfError::TryFromIntError(ref e) => Some(e),
+            IvfError::Vp9ParserError(ref e) => Some(e),
             _ => None,
         }
     }
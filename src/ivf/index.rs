@@ -0,0 +1,203 @@
+//! Frame index and seeking.
+
+use std::io::{Read, Seek, SeekFrom};
+
+use super::{regenerate_timestamp, FourCc, Ivf, IvfError, Result, FRAME_HEADER_SIZE, HEADER_SIZE};
+
+/// The position of a frame inside an IVF.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct IndexEntry {
+    /// Offset of the frame header from the start of the file.
+    pub offset: u64,
+    /// Size of the frame data.
+    pub size: u32,
+    /// The timestamp of the frame.
+    pub timestamp: u64,
+}
+
+impl<R: Read + Seek> Ivf<R> {
+    /// Returns the frame index, which is built on the first call.
+    ///
+    /// Only the frame headers are read to build the index. A truncated frame at the end of
+    /// the file is not part of the index. The position of the reader is not changed.
+    pub fn index(&mut self) -> Result<&[IndexEntry]> {
+        if self.index.is_none() {
+            self.index = Some(self.build_index()?);
+        }
+
+        Ok(self.index.as_deref().unwrap_or_default())
+    }
+
+    fn build_index(&mut self) -> Result<Vec<IndexEntry>> {
+        let position = self.reader.stream_position()?;
+        let end = self.reader.seek(SeekFrom::End(0))?;
+
+        let mut index = Vec::new();
+        let mut offset = u64::from(self.header.length.max(HEADER_SIZE));
+        let mut frame_header = [0u8; 12];
+        while offset + FRAME_HEADER_SIZE <= end {
+            let _ = self.reader.seek(SeekFrom::Start(offset))?;
+            self.reader.read_exact(&mut frame_header)?;
+
+            let mut size = [0u8; 4];
+            let mut timestamp = [0u8; 8];
+            size.copy_from_slice(&frame_header[..4]);
+            timestamp.copy_from_slice(&frame_header[4..]);
+            let size = u32::from_le_bytes(size);
+
+            let next = offset + FRAME_HEADER_SIZE + u64::from(size);
+            if next > end {
+                break;
+            }
+
+            let number = u64::try_from(index.len())?;
+            index.push(IndexEntry {
+                offset,
+                size,
+                timestamp: regenerate_timestamp(
+                    self.state.regenerate_timestamps,
+                    number,
+                    u64::from_le_bytes(timestamp),
+                ),
+            });
+            offset = next;
+        }
+
+        let _ = self.reader.seek(SeekFrom::Start(position))?;
+        Ok(index)
+    }
+
+    /// Seeks to the frame with the given number. The next call of `read_frame()` returns it.
+    pub fn seek_to_frame(&mut self, number: usize) -> Result<()> {
+        let entry = *self
+            .index()?
+            .get(number)
+            .ok_or(IvfError::FrameNotFound(number))?;
+        let _ = self.reader.seek(SeekFrom::Start(entry.offset))?;
+        self.state.last_timestamp = None;
+        self.state.frame_number = u64::try_from(number)?;
+        Ok(())
+    }
+
+    /// Seeks to the last frame with a timestamp that is less or equal to the given timestamp,
+    /// or to the first frame if there is none. Returns the number of the frame.
+    pub fn seek_to_timestamp(&mut self, timestamp: u64) -> Result<usize> {
+        let number = self.frame_at_timestamp(timestamp)?;
+        self.seek_to_frame(number)?;
+        Ok(number)
+    }
+
+    /// Seeks to the nearest key frame at or before the given timestamp, so decoding can
+    /// restart there with a new `Vp9Parser`. Returns the number of the frame.
+    ///
+    /// The packets are probed backwards from the frame at the timestamp. Only the first byte
+    /// of each packet is read, so corrupt packets are skipped. Only supported for VP9.
+    pub fn seek_to_keyframe(&mut self, timestamp: u64) -> Result<usize> {
+        if self.header.four_cc != FourCc::Vp9 {
+            return Err(IvfError::UnsupportedFourCc(self.header.four_cc));
+        }
+
+        let mut number = self.frame_at_timestamp(timestamp)?;
+        while number > 0 {
+            let entry = *self
+                .index()?
+                .get(number)
+                .ok_or(IvfError::FrameNotFound(number))?;
+            if self.probe_keyframe(&entry)? {
+                break;
+            }
+            number -= 1;
+        }
+
+        self.seek_to_frame(number)?;
+        Ok(number)
+    }
+
+    /// Reads the first byte of the packet of the entry and returns true if it starts with
+    /// a key frame.
+    fn probe_keyframe(&mut self, entry: &IndexEntry) -> Result<bool> {
+        if entry.size == 0 {
+            return Ok(false);
+        }
+
+        let _ = self
+            .reader
+            .seek(SeekFrom::Start(entry.offset + FRAME_HEADER_SIZE))?;
+        let mut byte = [0u8; 1];
+        self.reader.read_exact(&mut byte)?;
+        Ok(is_keyframe(&byte))
+    }
+
+    fn frame_at_timestamp(&mut self, timestamp: u64) -> Result<usize> {
+        let index = self.index()?;
+        if index.is_empty() {
+            return Err(IvfError::FrameNotFound(0));
+        }
+
+        let number = index
+            .iter()
+            .rposition(|entry| entry.timestamp <= timestamp)
+            .unwrap_or_default();
+        Ok(number)
+    }
+}
+
+/// Returns true if the first frame of the packet is a key frame.
+///
+/// Only the frame marker, the profile, `show_existing_frame` and `frame_type` are read, which
+/// are all inside the first byte.
+fn is_keyframe(packet: &[u8]) -> bool {
+    let byte = match packet.first() {
+        Some(byte) => *byte,
+        None => return false,
+    };
+
+    let frame_marker = byte >> 6;
+    if frame_marker != 2 {
+        return false;
+    }
+
+    let profile_low_bit = (byte >> 5) & 1;
+    let profile_high_bit = (byte >> 4) & 1;
+    // Profile 3 has an additional reserved bit.
+    let show_existing_frame_bit = if profile_high_bit == 1 && profile_low_bit == 1 {
+        2
+    } else {
+        3
+    };
+
+    let show_existing_frame = (byte >> show_existing_frame_bit) & 1;
+    let frame_type = (byte >> (show_existing_frame_bit - 1)) & 1;
+    show_existing_frame == 0 && frame_type == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bsf::show_existing_frame, Profile};
+
+    #[test]
+    fn probe_keyframe() {
+        // frame_marker, profile, show_existing_frame, frame_type, show_frame, ...
+        assert!(is_keyframe(&[0b1000_0010]));
+        assert!(is_keyframe(&[0b1010_0010]));
+        assert!(is_keyframe(&[0b1001_0010]));
+        assert!(is_keyframe(&[0b1011_0001]));
+        assert!(!is_keyframe(&[0b1000_0110]));
+        assert!(!is_keyframe(&[0b1011_0011]));
+
+        for profile in [
+            Profile::Profile0,
+            Profile::Profile1,
+            Profile::Profile2,
+            Profile::Profile3,
+        ] {
+            let packet = show_existing_frame(profile, 0).unwrap_or_default();
+            assert!(!is_keyframe(&packet));
+        }
+
+        assert!(!is_keyframe(&[]));
+        assert!(!is_keyframe(&[0x00]));
+        assert!(!is_keyframe(&[0xFF]));
+    }
+}
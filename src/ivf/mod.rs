@@ -2,71 +2,158 @@
 
 use std::{
     convert::{TryFrom, TryInto},
-    io::Read,
+    io::{ErrorKind, Read},
 };
 
-pub use error::IvfError;
+use crate::Limits;
 
+#[cfg(feature = "async")]
+pub use async_reader::AsyncIvf;
+pub use error::IvfError;
+pub use index::IndexEntry;
+pub use slice::{FrameRef, IvfSlice};
+pub use stream::{StreamFrame, Vp9Stream};
+pub use timestamp::{Timebase, TimestampAnomaly, TimestampChecker};
+pub use validate::{HeaderReport, HeaderWarning};
+pub use writer::IvfWriter;
+
+#[cfg(feature = "async")]
+mod async_reader;
 mod error;
+mod index;
+mod slice;
+mod stream;
+mod timestamp;
+mod validate;
+mod writer;
 
 type Result<T> = std::result::Result<T, IvfError>;
 
-/// IVF is a simple container format for raw VP9 data.
+/// Size of the IVF header in bytes.
+const HEADER_SIZE: u16 = 32;
+
+/// Size of the header in front of every frame in bytes.
+const FRAME_HEADER_SIZE: u64 = 12;
+
+/// The codec of the frames inside an IVF.
+#[derive(Clone, Copy, Debug, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub enum FourCc {
+    /// VP8 (`VP80`).
+    Vp8,
+    /// VP9 (`VP90`).
+    Vp9,
+    /// AV1 (`AV01`).
+    Av1,
+    /// Any other codec.
+    Other([u8; 4]),
+}
+
+impl From<[u8; 4]> for FourCc {
+    fn from(four_cc: [u8; 4]) -> Self {
+        match &four_cc {
+            b"VP80" => FourCc::Vp8,
+            b"VP90" => FourCc::Vp9,
+            b"AV01" => FourCc::Av1,
+            _ => FourCc::Other(four_cc),
+        }
+    }
+}
+
+impl From<FourCc> for [u8; 4] {
+    fn from(four_cc: FourCc) -> Self {
+        match four_cc {
+            FourCc::Vp8 => *b"VP80",
+            FourCc::Vp9 => *b"VP90",
+            FourCc::Av1 => *b"AV01",
+            FourCc::Other(four_cc) => four_cc,
+        }
+    }
+}
+
+/// Options for reading an IVF.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct IvfOptions {
+    /// Rejects files with a version other than 0.
+    pub check_version: bool,
+    /// Rejects files with a header length other than 32. If disabled, a longer header is
+    /// skipped.
+    pub check_length: bool,
+    /// The limits for the frames. Only the maximum packet size applies to IVF.
+    pub limits: Limits,
+    /// Recovers from truncated and corrupted frames instead of returning an error.
+    ///
+    /// A truncated final frame is returned with the partial data and `Ivf::is_truncated()`
+    /// is set. If a frame header is not plausible, the reader scans forward byte by byte
+    /// until it finds one that is. A plausible frame header has a size within the maximum
+    /// packet size, a timestamp that is less than 2^32 ticks before or after the previous one,
+    /// which allows repeated and reordered timestamps, and for VP9 a packet that starts with a
+    /// frame marker. The timestamp of the first frame is not checked. Wrap unbuffered readers
+    /// into a `BufReader` when using this option.
+    pub recover: bool,
+    /// Replaces the timestamps with the frame number multiplied by the given frame duration
+    /// in ticks of the timebase. Meant for files with broken timestamps.
+    pub regenerate_timestamps: Option<u64>,
+}
+
+impl Default for IvfOptions {
+    fn default() -> Self {
+        Self {
+            check_version: true,
+            check_length: true,
+            limits: Limits::default(),
+            recover: false,
+            regenerate_timestamps: None,
+        }
+    }
+}
+
+/// IVF is a simple container format for raw video data.
+///
+/// IVF itself is codec agnostic. Check `four_cc()` before handing the packets to
+/// the `Vp9Parser`.
 #[derive(Debug, Clone)]
 pub struct Ivf<R> {
     reader: R,
     header: IvfHeader,
-
-    size_buffer: [u8; 4],
-    timestamp_buffer: [u8; 8],
+    index: Option<Vec<IndexEntry>>,
+    state: ReadState,
 }
 
 impl<R: Read> Ivf<R> {
     /// Creates a new IVF using the given reader.
-    pub fn new(mut reader: R) -> Result<Self> {
-        let mut d = vec![0u8; std::mem::size_of::<IvfHeader>()];
-        reader.read_exact(&mut d)?;
-
-        let header = IvfHeader {
-            signature: [d[0], d[1], d[2], d[3]],
-            version: u16::from_le_bytes(d[4..=5].try_into()?),
-            length: u16::from_le_bytes(d[6..=7].try_into()?),
-            four_cc: [d[8], d[9], d[10], d[11]],
-            width: u16::from_le_bytes(d[12..=13].try_into()?),
-            height: u16::from_le_bytes(d[14..=15].try_into()?),
-            frame_rate_rate: u32::from_le_bytes(d[16..=19].try_into()?),
-            frame_rate_scale: u32::from_le_bytes(d[20..=23].try_into()?),
-            frame_count: u32::from_le_bytes(d[24..=27].try_into()?),
-            _reserved: [d[28], d[29], d[30], d[31]],
-        };
-
-        if header.signature != [0x44, 0x4B, 0x49, 0x46] {
-            return Err(IvfError::InvalidHeader("invalid signature".to_owned()));
-        }
-
-        if header.version != 0 {
-            return Err(IvfError::InvalidHeader("invalid version".to_owned()));
-        }
+    pub fn new(reader: R) -> Result<Self> {
+        Self::with_options(reader, &IvfOptions::default())
+    }
 
-        if header.length != 32 {
-            return Err(IvfError::InvalidHeader("invalid length".to_owned()));
-        }
+    /// Creates a new IVF using the given reader and options.
+    pub fn with_options(mut reader: R, options: &IvfOptions) -> Result<Self> {
+        let mut d = vec![0u8; usize::from(HEADER_SIZE)];
+        reader.read_exact(&mut d)?;
+        let header = IvfHeader::parse(&d, options)?;
 
-        if header.four_cc != [0x56, 0x50, 0x39, 0x30] {
-            return Err(IvfError::InvalidHeader(format!(
-                "invalid four_cc: {:?}",
-                header.four_cc
-            )));
+        if header.length > HEADER_SIZE {
+            let mut extension = vec![0u8; usize::from(header.length - HEADER_SIZE)];
+            reader.read_exact(&mut extension)?;
         }
 
         Ok(Self {
             reader,
             header,
-            size_buffer: [0u8; 4],
-            timestamp_buffer: [0u8; 8],
+            index: None,
+            state: ReadState::new(options),
         })
     }
 
+    /// The codec of the frames.
+    pub fn four_cc(&self) -> FourCc {
+        self.header.four_cc
+    }
+
+    /// The version of the IVF.
+    pub fn version(&self) -> u16 {
+        self.header.version
+    }
+
     /// The initial width of the video.
     pub fn width(&self) -> u16 {
         self.header.width
@@ -95,31 +182,301 @@ impl<R: Read> Ivf<R> {
         self.header.frame_count
     }
 
+    /// The timebase of the timestamps, `frame_rate_scale / frame_rate_rate` seconds.
+    pub fn timebase(&self) -> Timebase {
+        self.header.timebase()
+    }
+
+    /// Returns true if the file ended in the middle of a frame. Only set in recovery mode,
+    /// otherwise an `UnexpectedFileEnding` error is returned.
+    pub fn is_truncated(&self) -> bool {
+        self.state.truncated
+    }
+
+    /// Number of bytes that were skipped to resynchronize after a corrupted frame.
+    /// Only counted in recovery mode.
+    pub fn skipped_bytes(&self) -> u64 {
+        self.state.skipped_bytes
+    }
+
     /// Reads the next frame inside the IVF. Returns `None` if the end of the file has been reached.
     ///
     /// A frame contains a VP9 bitstream packet which can contain either a normal frame or a super frame.
     pub fn read_frame(&mut self) -> Result<Option<Frame>> {
-        if self.reader.read_exact(&mut self.size_buffer).is_err() {
+        let mut data = Vec::new();
+        let timestamp = self.read_frame_into(&mut data)?;
+
+        Ok(timestamp.map(|timestamp| Frame {
+            timestamp,
+            packet: data,
+        }))
+    }
+
+    /// Reads the next frame into the given buffer and returns its timestamp. Returns `None`
+    /// if the end of the file has been reached.
+    ///
+    /// The buffer is resized to the size of the packet. Reusing the buffer avoids an allocation
+    /// for every frame.
+    pub fn read_frame_into(&mut self, buffer: &mut Vec<u8>) -> Result<Option<u64>> {
+        buffer.clear();
+
+        let mut frame_header = [0u8; 12];
+        let read = read_full(&mut self.reader, &mut frame_header)?;
+        if !self.state.frame_header_read(read)? {
             return Ok(None);
         }
-        if self.reader.read_exact(&mut self.timestamp_buffer).is_err() {
-            return Err(IvfError::UnexpectedFileEnding);
+
+        let timestamp = if self.state.recover {
+            match self.recover_frame(frame_header, buffer)? {
+                Some(timestamp) => timestamp,
+                None => return Ok(None),
+            }
+        } else {
+            let (size, timestamp) = self.state.parse_frame_header(&frame_header)?;
+            self.read_packet(size, buffer)?;
+            timestamp
+        };
+
+        Ok(Some(self.state.next_timestamp(timestamp)))
+    }
+
+    /// Reads a packet of the given size into the buffer.
+    ///
+    /// The buffer grows with the data that is actually read, so a corrupt size of a
+    /// truncated file doesn't allocate more memory than the file contains.
+    fn read_packet(&mut self, size: usize, buffer: &mut Vec<u8>) -> Result<()> {
+        let read = (&mut self.reader)
+            .take(u64::try_from(size)?)
+            .read_to_end(buffer)?;
+        self.state.packet_read(size, read)
+    }
+
+    /// Reads the frame of the given header, resynchronizing if the header is not plausible.
+    fn recover_frame(
+        &mut self,
+        frame_header: [u8; 12],
+        buffer: &mut Vec<u8>,
+    ) -> Result<Option<u64>> {
+        let mut resync = Resync::new(frame_header);
+        loop {
+            match resync.step(&self.state, self.header.four_cc)? {
+                ResyncStep::NeedByte => {
+                    let mut byte = [0u8; 1];
+                    if read_full(&mut self.reader, &mut byte)? == 0 {
+                        return resync.end_of_file(&mut self.state);
+                    }
+                    resync.push(byte[0]);
+                }
+                ResyncStep::Found { size, timestamp } => {
+                    let remaining = resync.found(&mut self.state, timestamp, buffer);
+                    self.read_packet(size.saturating_sub(remaining), buffer)?;
+                    return Ok(Some(timestamp));
+                }
+            }
         }
+    }
+}
 
-        let size = usize::try_from(u32::from_le_bytes(self.size_buffer))?;
-        let timestamp = u64::from_le_bytes(self.timestamp_buffer);
+/// The state of reading frames that doesn't depend on the reader.
+#[derive(Debug, Clone)]
+struct ReadState {
+    limits: Limits,
+    recover: bool,
+    truncated: bool,
+    skipped_bytes: u64,
+    last_timestamp: Option<u64>,
+    regenerate_timestamps: Option<u64>,
+    frame_number: u64,
+}
 
-        let mut data = vec![0u8; size];
+impl ReadState {
+    fn new(options: &IvfOptions) -> Self {
+        Self {
+            limits: options.limits,
+            recover: options.recover,
+            truncated: false,
+            skipped_bytes: 0,
+            last_timestamp: None,
+            regenerate_timestamps: options.regenerate_timestamps,
+            frame_number: 0,
+        }
+    }
 
-        if self.reader.read_exact(&mut data).is_err() {
+    /// Checks how many bytes of a frame header could be read. Returns false at the end
+    /// of the file.
+    ///
+    /// Outside of recovery mode, a file that ends inside the size field of a frame header
+    /// ends cleanly, a file that ends inside the timestamp is an error.
+    fn frame_header_read(&mut self, read: usize) -> Result<bool> {
+        if read == 0 {
+            return Ok(false);
+        }
+        if read < 12 {
+            if self.recover {
+                self.truncated = true;
+                return Ok(false);
+            }
+            if read < 4 {
+                return Ok(false);
+            }
             return Err(IvfError::UnexpectedFileEnding);
         }
 
-        Ok(Some(Frame {
-            timestamp,
-            packet: data,
-        }))
+        Ok(true)
+    }
+
+    /// Returns the packet size and timestamp of a frame header and checks the packet size.
+    fn parse_frame_header(&self, frame_header: &[u8]) -> Result<(usize, u64)> {
+        let (size, timestamp) = parse_frame_header(frame_header)?;
+        self.limits.check_packet_size(size)?;
+        Ok((size, timestamp))
+    }
+
+    /// Checks how many bytes of a packet could be read.
+    fn packet_read(&mut self, size: usize, read: usize) -> Result<()> {
+        if read != size {
+            if self.recover {
+                self.truncated = true;
+            } else {
+                return Err(IvfError::UnexpectedFileEnding);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns true if a frame header found in recovery mode is plausible.
+    ///
+    /// The timestamp may repeat or go backwards, but must stay within 2^32 ticks of the
+    /// previous one. The timestamp of the first frame is not checked.
+    fn is_plausible(&self, size: usize, timestamp: u64) -> bool {
+        let timestamp_plausible = match self.last_timestamp {
+            Some(last_timestamp) => timestamp.abs_diff(last_timestamp) <= u64::from(u32::MAX),
+            None => true,
+        };
+        self.limits.check_packet_size(size).is_ok() && timestamp_plausible
+    }
+
+    /// Returns the timestamp of the next frame, which is regenerated if enabled.
+    fn next_timestamp(&mut self, timestamp: u64) -> u64 {
+        let number = self.frame_number;
+        self.frame_number += 1;
+        regenerate_timestamp(self.regenerate_timestamps, number, timestamp)
+    }
+}
+
+/// A step of the search for a plausible frame header.
+enum ResyncStep {
+    /// The next byte of the file is needed.
+    NeedByte,
+    /// A plausible frame header was found.
+    Found { size: usize, timestamp: u64 },
+}
+
+/// Searches for a plausible frame header in recovery mode. The bytes of the file are fed
+/// by the caller, so the search works with any reader.
+///
+/// The window holds the candidate frame header followed by the first byte of its packet.
+struct Resync {
+    window: Vec<u8>,
+    skipped_bytes: u64,
+}
+
+impl Resync {
+    fn new(frame_header: [u8; 12]) -> Self {
+        Self {
+            window: frame_header.to_vec(),
+            skipped_bytes: 0,
+        }
+    }
+
+    fn step(&mut self, state: &ReadState, four_cc: FourCc) -> Result<ResyncStep> {
+        loop {
+            let (size, timestamp) = parse_frame_header(&self.window[..12])?;
+            let header_plausible = state.is_plausible(size, timestamp);
+            let needs_marker = header_plausible && size > 0 && four_cc == FourCc::Vp9;
+
+            if needs_marker && self.window.len() == 12 {
+                return Ok(ResyncStep::NeedByte);
+            }
+
+            let plausible = match self.window.get(12) {
+                Some(first_byte) if needs_marker => first_byte >> 6 == 0b10,
+                _ => header_plausible,
+            };
+
+            if plausible {
+                return Ok(ResyncStep::Found { size, timestamp });
+            }
+
+            let _ = self.window.remove(0);
+            self.skipped_bytes += 1;
+            if self.window.len() < 12 {
+                return Ok(ResyncStep::NeedByte);
+            }
+        }
+    }
+
+    fn push(&mut self, byte: u8) {
+        self.window.push(byte);
+    }
+
+    /// Updates the state with the found frame header.
+    fn accept(&self, state: &mut ReadState, timestamp: u64) {
+        state.skipped_bytes += self.skipped_bytes;
+        state.last_timestamp = Some(timestamp);
+    }
+
+    /// Moves the start of the packet into the buffer and returns its length.
+    fn found(self, state: &mut ReadState, timestamp: u64, buffer: &mut Vec<u8>) -> usize {
+        self.accept(state, timestamp);
+        buffer.extend_from_slice(self.window.get(12..).unwrap_or_default());
+        buffer.len()
+    }
+
+    /// Returns the frame to return if the file ends during the search.
+    ///
+    /// If the file ends right after the original frame header, the frame is truncated.
+    fn end_of_file(self, state: &mut ReadState) -> Result<Option<u64>> {
+        state.skipped_bytes += self.skipped_bytes;
+        if self.skipped_bytes > 0 {
+            return Ok(None);
+        }
+
+        state.truncated = true;
+        let (_, timestamp) = parse_frame_header(&self.window)?;
+        Ok(Some(timestamp))
+    }
+}
+
+/// Returns the timestamp of the frame with the given number if timestamps are regenerated.
+fn regenerate_timestamp(frame_duration: Option<u64>, number: u64, timestamp: u64) -> u64 {
+    match frame_duration {
+        Some(frame_duration) => number.saturating_mul(frame_duration),
+        None => timestamp,
+    }
+}
+
+/// Returns the packet size and timestamp of a frame header.
+fn parse_frame_header(frame_header: &[u8]) -> Result<(usize, u64)> {
+    let size = u32::from_le_bytes(frame_header[..4].try_into()?);
+    let timestamp = u64::from_le_bytes(frame_header[4..12].try_into()?);
+    Ok((usize::try_from(size)?, timestamp))
+}
+
+/// Fills the buffer like `read_exact`, but returns the number of bytes read if the reader
+/// ends early, so that a clean end can be told apart from a truncated file.
+fn read_full<R: Read>(reader: &mut R, buffer: &mut [u8]) -> std::io::Result<usize> {
+    let mut read = 0;
+    while read < buffer.len() {
+        match reader.read(&mut buffer[read..]) {
+            Ok(0) => break,
+            Ok(n) => read += n,
+            Err(err) if err.kind() == ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
     }
+    Ok(read)
 }
 
 /// The IVF Header.
@@ -128,7 +485,7 @@ struct IvfHeader {
     signature: [u8; 4],
     version: u16,
     length: u16,
-    four_cc: [u8; 4],
+    four_cc: FourCc,
     width: u16,
     height: u16,
     frame_rate_rate: u32,
@@ -137,6 +494,46 @@ struct IvfHeader {
     _reserved: [u8; 4],
 }
 
+impl IvfHeader {
+    /// Parses and validates the header.
+    fn parse(d: &[u8], options: &IvfOptions) -> Result<Self> {
+        if d.len() < usize::from(HEADER_SIZE) {
+            return Err(IvfError::UnexpectedFileEnding);
+        }
+
+        let header = IvfHeader {
+            signature: [d[0], d[1], d[2], d[3]],
+            version: u16::from_le_bytes(d[4..=5].try_into()?),
+            length: u16::from_le_bytes(d[6..=7].try_into()?),
+            four_cc: [d[8], d[9], d[10], d[11]].into(),
+            width: u16::from_le_bytes(d[12..=13].try_into()?),
+            height: u16::from_le_bytes(d[14..=15].try_into()?),
+            frame_rate_rate: u32::from_le_bytes(d[16..=19].try_into()?),
+            frame_rate_scale: u32::from_le_bytes(d[20..=23].try_into()?),
+            frame_count: u32::from_le_bytes(d[24..=27].try_into()?),
+            _reserved: [d[28], d[29], d[30], d[31]],
+        };
+
+        if header.signature != [0x44, 0x4B, 0x49, 0x46] {
+            return Err(IvfError::InvalidHeader("invalid signature".to_owned()));
+        }
+
+        if options.check_version && header.version != 0 {
+            return Err(IvfError::InvalidHeader("invalid version".to_owned()));
+        }
+
+        if options.check_length && header.length != HEADER_SIZE {
+            return Err(IvfError::InvalidHeader("invalid length".to_owned()));
+        }
+
+        Ok(header)
+    }
+
+    fn timebase(&self) -> Timebase {
+        Timebase::new(self.frame_rate_scale, self.frame_rate_rate)
+    }
+}
+
 /// Frame inside an IVF.
 ///
 /// A frame can contain a VP9 bitstream packet which contains either a frame or a super frame.
@@ -392,4 +789,55 @@ mod tests {
 
         assert_eq!(count, 29);
     }
+
+    #[test]
+    fn parse_other_codecs() {
+        let mut header: Vec<u8> = vec![
+            0x44, 0x4B, 0x49, 0x46, 0x00, 0x00, 0x20, 0x00, 0x41, 0x56, 0x30, 0x31, 0xB0, 0x00,
+            0x90, 0x00, 0x30, 0x75, 0x00, 0x00, 0xE8, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00,
+        ];
+
+        let ivf = Ivf::new(Cursor::new(&header)).unwrap();
+        assert_eq!(ivf.four_cc(), FourCc::Av1);
+
+        header[8..12].copy_from_slice(b"XVID");
+        let ivf = Ivf::new(Cursor::new(&header)).unwrap();
+        assert_eq!(ivf.four_cc(), FourCc::Other(*b"XVID"));
+        assert_eq!(<[u8; 4]>::from(ivf.four_cc()), *b"XVID");
+    }
+
+    #[test]
+    fn parse_with_options() {
+        // Version 1 and a header length of 36.
+        let mut data: Vec<u8> = vec![
+            0x44, 0x4B, 0x49, 0x46, 0x01, 0x00, 0x24, 0x00, 0x56, 0x50, 0x38, 0x30, 0xB0, 0x00,
+            0x90, 0x00, 0x30, 0x75, 0x00, 0x00, 0xE8, 0x03, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF,
+        ];
+        data.extend_from_slice(&[0x01, 0x00, 0x00, 0x00, 0x07, 0x00, 0x00, 0x00]);
+        data.extend_from_slice(&[0x00, 0x00, 0x00, 0x00, 0xAB]);
+
+        assert!(Ivf::new(Cursor::new(&data)).is_err());
+
+        let options = IvfOptions {
+            check_version: true,
+            check_length: false,
+            ..Default::default()
+        };
+        assert!(Ivf::with_options(Cursor::new(&data), &options).is_err());
+
+        let options = IvfOptions {
+            check_version: false,
+            check_length: false,
+            ..Default::default()
+        };
+        let mut ivf = Ivf::with_options(Cursor::new(&data), &options).unwrap();
+        assert_eq!(ivf.four_cc(), FourCc::Vp8);
+        assert_eq!(ivf.version(), 1);
+
+        let frame = ivf.read_frame().unwrap().unwrap();
+        assert_eq!(frame.timestamp, 7);
+        assert_eq!(frame.packet, vec![0xAB]);
+    }
 }
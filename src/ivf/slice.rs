@@ -0,0 +1,188 @@
+//! IVF parsing over in-memory buffers.
+
+use std::convert::{TryFrom, TryInto};
+
+use super::{
+    FourCc, IvfError, IvfHeader, IvfOptions, ReadState, Result, Resync, ResyncStep, Timebase,
+    HEADER_SIZE,
+};
+
+/// An IVF that is parsed from a buffer, for example a memory mapped file.
+///
+/// The frames borrow their packets from the buffer, so no data is copied.
+#[derive(Debug, Clone)]
+pub struct IvfSlice<'a> {
+    data: &'a [u8],
+    pub(super) header: IvfHeader,
+    position: usize,
+    state: ReadState,
+}
+
+impl<'a> IvfSlice<'a> {
+    /// Creates a new IVF over the given buffer.
+    pub fn new(data: &'a [u8]) -> Result<Self> {
+        Self::with_options(data, &IvfOptions::default())
+    }
+
+    /// Creates a new IVF over the given buffer using the given options.
+    pub fn with_options(data: &'a [u8], options: &IvfOptions) -> Result<Self> {
+        let header = IvfHeader::parse(data, options)?;
+        let position = usize::from(header.length.max(HEADER_SIZE));
+        if position > data.len() {
+            return Err(IvfError::UnexpectedFileEnding);
+        }
+
+        Ok(Self {
+            data,
+            header,
+            position,
+            state: ReadState::new(options),
+        })
+    }
+
+    /// The codec of the frames.
+    pub fn four_cc(&self) -> FourCc {
+        self.header.four_cc
+    }
+
+    /// The version of the IVF.
+    pub fn version(&self) -> u16 {
+        self.header.version
+    }
+
+    /// The initial width of the video.
+    pub fn width(&self) -> u16 {
+        self.header.width
+    }
+
+    /// The initial height of the video.
+    pub fn height(&self) -> u16 {
+        self.header.height
+    }
+
+    /// The framerate of the video (frame_rate_rate * frame_rate_scale).
+    pub fn frame_rate_rate(&self) -> u32 {
+        self.header.frame_rate_rate
+    }
+
+    /// Divider of the seconds.
+    pub fn frame_rate_scale(&self) -> u32 {
+        self.header.frame_rate_scale
+    }
+
+    /// Number of frames stored inside the IVF. A frame can contain a frame or a super frame.
+    pub fn frame_count(&self) -> u32 {
+        self.header.frame_count
+    }
+
+    /// The timebase of the timestamps, `frame_rate_scale / frame_rate_rate` seconds.
+    pub fn timebase(&self) -> Timebase {
+        self.header.timebase()
+    }
+
+    /// Returns true if the buffer ended in the middle of a frame. Only set in recovery mode,
+    /// otherwise an `UnexpectedFileEnding` error is returned.
+    pub fn is_truncated(&self) -> bool {
+        self.state.truncated
+    }
+
+    /// Number of bytes that were skipped to resynchronize after a corrupted frame.
+    /// Only counted in recovery mode.
+    pub fn skipped_bytes(&self) -> u64 {
+        self.state.skipped_bytes
+    }
+
+    /// Returns a copy that reads the frames from the start of the buffer.
+    pub(super) fn frames(&self) -> IvfSlice<'a> {
+        Self {
+            position: usize::from(self.header.length.max(HEADER_SIZE)),
+            state: ReadState {
+                truncated: false,
+                skipped_bytes: 0,
+                last_timestamp: None,
+                frame_number: 0,
+                ..self.state.clone()
+            },
+            ..self.clone()
+        }
+    }
+
+    /// Reads the next frame inside the IVF. Returns `None` if the end of the buffer has been
+    /// reached.
+    pub fn read_frame(&mut self) -> Result<Option<FrameRef<'a>>> {
+        let data = self.data.get(self.position..).unwrap_or_default();
+        if !self.state.frame_header_read(data.len().min(12))? {
+            self.position = self.data.len();
+            return Ok(None);
+        }
+
+        let frame_header: [u8; 12] = data[..12].try_into()?;
+        let (start, size, timestamp) = if self.state.recover {
+            match self.recover_frame(frame_header)? {
+                Some(frame) => frame,
+                None => return Ok(None),
+            }
+        } else {
+            let (size, timestamp) = self.state.parse_frame_header(&frame_header)?;
+            (self.position + 12, size, timestamp)
+        };
+
+        let available = self.data.len() - start;
+        self.state.packet_read(size, size.min(available))?;
+        let end = start + size.min(available);
+        let packet = &self.data[start..end];
+        self.position = end;
+
+        let timestamp = self.state.next_timestamp(timestamp);
+        Ok(Some(FrameRef { timestamp, packet }))
+    }
+
+    /// Finds the frame of the given header, resynchronizing if the header is not plausible.
+    /// Returns the start of the packet, its size and the timestamp.
+    fn recover_frame(&mut self, frame_header: [u8; 12]) -> Result<Option<(usize, usize, u64)>> {
+        let mut resync = Resync::new(frame_header);
+        let mut next = self.position + 12;
+        loop {
+            match resync.step(&self.state, self.header.four_cc)? {
+                ResyncStep::NeedByte => match self.data.get(next) {
+                    Some(byte) => {
+                        resync.push(*byte);
+                        next += 1;
+                    }
+                    None => {
+                        self.position = self.data.len();
+                        let timestamp = resync.end_of_file(&mut self.state)?;
+                        return Ok(timestamp.map(|timestamp| (self.data.len(), 0, timestamp)));
+                    }
+                },
+                ResyncStep::Found { size, timestamp } => {
+                    resync.accept(&mut self.state, timestamp);
+                    let start = self.position + usize::try_from(resync.skipped_bytes)? + 12;
+                    return Ok(Some((start, size, timestamp)));
+                }
+            }
+        }
+    }
+}
+
+impl<'a> Iterator for IvfSlice<'a> {
+    type Item = Result<FrameRef<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let frame = self.read_frame();
+        if frame.is_err() {
+            // Stop after an error instead of returning it forever.
+            self.position = self.data.len();
+        }
+        frame.transpose()
+    }
+}
+
+/// Frame inside an IVF that borrows its packet.
+#[derive(Debug, Clone, Copy)]
+pub struct FrameRef<'a> {
+    /// The timestamp of the frame.
+    pub timestamp: u64,
+    /// The data packet of the frame.
+    pub packet: &'a [u8],
+}
@@ -0,0 +1,124 @@
+//! Reading and parsing of the VP9 frames of an IVF in one step.
+
+use std::{collections::VecDeque, io::Read, ops::Range, sync::Arc};
+
+use super::{FourCc, Ivf, IvfError, IvfOptions, Result};
+use crate::{OwnedFrame, Vp9Parser};
+
+/// A parsed VP9 frame of a [`Vp9Stream`] that owns its data.
+#[derive(Clone, Debug)]
+pub struct StreamFrame {
+    /// The timestamp of the IVF frame that contains the frame.
+    pub timestamp: u64,
+    /// The index of the frame inside the super frame. Zero for packets without a super frame.
+    pub index: usize,
+    /// Indicates that the frame is displayed, either by `show_frame` or
+    /// `show_existing_frame`.
+    pub visible: bool,
+    /// The byte range of the frame inside the packet of the IVF frame.
+    pub range: Range<usize>,
+    /// The parsed frame. The frames of a packet share the packet buffer.
+    pub frame: OwnedFrame,
+}
+
+/// Reads the packets of an IVF and parses the VP9 frames inside them.
+///
+/// Replaces the loop of reading a frame with [`Ivf`] and handing the packet to a
+/// [`Vp9Parser`]. The parser sees every packet in order, so its reference state is always
+/// correct. The iterator stops after an IVF error. Errors of single VP9 frames are
+/// returned, but the following frames are still parsed.
+#[derive(Debug)]
+pub struct Vp9Stream<R> {
+    ivf: Ivf<R>,
+    parser: Vp9Parser,
+    packet: Vec<u8>,
+    frames: VecDeque<Result<StreamFrame>>,
+    finished: bool,
+}
+
+impl<R: Read> Vp9Stream<R> {
+    /// Creates a new stream using the given reader.
+    pub fn new(reader: R) -> Result<Self> {
+        Self::with_options(reader, &IvfOptions::default())
+    }
+
+    /// Creates a new stream using the given reader and options. The limits of the options
+    /// are also used for the parser.
+    ///
+    /// Returns an error if the IVF doesn't contain VP9.
+    pub fn with_options(reader: R, options: &IvfOptions) -> Result<Self> {
+        let ivf = Ivf::with_options(reader, options)?;
+        if ivf.four_cc() != FourCc::Vp9 {
+            return Err(IvfError::UnsupportedFourCc(ivf.four_cc()));
+        }
+
+        Ok(Self {
+            ivf,
+            parser: Vp9Parser::with_limits(options.limits),
+            packet: Vec::new(),
+            frames: VecDeque::new(),
+            finished: false,
+        })
+    }
+
+    /// The IVF the packets are read from.
+    pub fn ivf(&self) -> &Ivf<R> {
+        &self.ivf
+    }
+
+    /// Reads the next frame. Returns `None` if the end of the file has been reached.
+    pub fn read_frame(&mut self) -> Result<Option<StreamFrame>> {
+        self.next().transpose()
+    }
+
+    /// Reads the next packet and parses its frames. Returns false at the end of the file.
+    fn read_packet(&mut self) -> Result<bool> {
+        let timestamp = match self.ivf.read_frame_into(&mut self.packet)? {
+            Some(timestamp) => timestamp,
+            None => return Ok(false),
+        };
+
+        let packet = Arc::from(self.packet.as_slice());
+        let frames = self.parser.parse_shared_packet(packet);
+        for (index, frame) in frames.into_iter().enumerate() {
+            let frame = frame.map_err(IvfError::from).map(|frame| StreamFrame {
+                timestamp,
+                index,
+                visible: frame.show_frame() || frame.show_existing_frame(),
+                range: frame.shared_range().unwrap_or_default(),
+                frame,
+            });
+            self.frames.push_back(frame);
+        }
+
+        Ok(true)
+    }
+}
+
+impl<R: Read> Iterator for Vp9Stream<R> {
+    type Item = Result<StreamFrame>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(frame) = self.frames.pop_front() {
+                return Some(frame);
+            }
+            if self.finished {
+                return None;
+            }
+
+            match self.read_packet() {
+                Ok(true) => {}
+                Ok(false) => {
+                    self.finished = true;
+                    return None;
+                }
+                Err(err) => {
+                    // Stop after an error instead of returning it forever.
+                    self.finished = true;
+                    return Some(Err(err));
+                }
+            }
+        }
+    }
+}
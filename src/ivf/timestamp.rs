@@ -0,0 +1,233 @@
+//! Timebases and timestamp checks.
+
+use std::{
+    convert::TryFrom,
+    io::{Read, Seek},
+    time::Duration,
+};
+
+use super::{Ivf, Result};
+
+const NANOS_PER_SECOND: u128 = 1_000_000_000;
+
+/// A rational timebase. A timestamp is a number of ticks of `numerator / denominator` seconds.
+///
+/// IVF timestamps use `frame_rate_scale / frame_rate_rate` seconds.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct Timebase {
+    /// The numerator of the timebase.
+    pub numerator: u32,
+    /// The denominator of the timebase.
+    pub denominator: u32,
+}
+
+impl Timebase {
+    /// Creates a timebase of `numerator / denominator` seconds.
+    pub fn new(numerator: u32, denominator: u32) -> Self {
+        Self {
+            numerator,
+            denominator,
+        }
+    }
+
+    /// Converts a timestamp into a duration, rounding down to whole nanoseconds.
+    ///
+    /// Returns `None` if the denominator is zero or the duration overflows.
+    pub fn to_duration(&self, timestamp: u64) -> Option<Duration> {
+        let denominator = u128::from(self.denominator);
+        if denominator == 0 {
+            return None;
+        }
+
+        let ticks = u128::from(timestamp) * u128::from(self.numerator);
+        let seconds = u64::try_from(ticks / denominator).ok()?;
+        let nanos = u32::try_from((ticks % denominator) * NANOS_PER_SECOND / denominator).ok()?;
+        Some(Duration::new(seconds, nanos))
+    }
+
+    /// Converts a duration into a timestamp, rounding to the nearest tick.
+    ///
+    /// Returns `None` if the numerator is zero or the timestamp overflows.
+    pub fn from_duration(&self, duration: Duration) -> Option<u64> {
+        let divisor = u128::from(self.numerator) * NANOS_PER_SECOND;
+        if divisor == 0 {
+            return None;
+        }
+
+        let nanos = duration
+            .as_nanos()
+            .checked_mul(u128::from(self.denominator))?;
+        u64::try_from((nanos + divisor / 2) / divisor).ok()
+    }
+
+    /// Converts a timestamp into the given timebase, rounding to the nearest tick.
+    ///
+    /// Returns `None` if a numerator or denominator is zero or the timestamp overflows.
+    pub fn rescale(&self, timestamp: u64, target: Timebase) -> Option<u64> {
+        let dividend = u128::from(self.numerator) * u128::from(target.denominator);
+        let divisor = u128::from(self.denominator) * u128::from(target.numerator);
+        if divisor == 0 {
+            return None;
+        }
+
+        let ticks = u128::from(timestamp).checked_mul(dividend)?;
+        u64::try_from(ticks.checked_add(divisor / 2)? / divisor).ok()
+    }
+}
+
+/// An irregularity of the timestamps of consecutive frames.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TimestampAnomaly {
+    /// The timestamp is smaller than the timestamp of the previous frame.
+    NonMonotonic {
+        /// The number of the frame.
+        frame: usize,
+        /// The timestamp of the frame.
+        timestamp: u64,
+        /// The timestamp of the previous frame.
+        previous: u64,
+    },
+    /// The timestamp is the same as the timestamp of the previous frame.
+    Duplicate {
+        /// The number of the frame.
+        frame: usize,
+        /// The timestamp of the frame.
+        timestamp: u64,
+    },
+    /// At least one frame duration is missing between the frame and the previous frame.
+    Gap {
+        /// The number of the frame.
+        frame: usize,
+        /// The timestamp of the frame.
+        timestamp: u64,
+        /// The timestamp of the previous frame.
+        previous: u64,
+    },
+}
+
+/// Checks the timestamps of consecutive frames for anomalies.
+///
+/// A gap is reported if the distance to the previous timestamp is at least one and a half
+/// frame durations, so that timestamps which were rounded to the timebase are not reported.
+#[derive(Clone, Debug)]
+pub struct TimestampChecker {
+    frame_duration: u64,
+    previous: Option<u64>,
+    frame: usize,
+}
+
+impl TimestampChecker {
+    /// Creates a new checker for frames with the given duration in ticks of the timebase.
+    pub fn new(frame_duration: u64) -> Self {
+        Self {
+            frame_duration: frame_duration.max(1),
+            previous: None,
+            frame: 0,
+        }
+    }
+
+    /// Checks the timestamp of the next frame.
+    pub fn check(&mut self, timestamp: u64) -> Option<TimestampAnomaly> {
+        let frame = self.frame;
+        self.frame += 1;
+        let previous = self.previous.replace(timestamp)?;
+        // Compares against one and a half frame durations without rounding.
+        let is_gap = timestamp.saturating_sub(previous).saturating_mul(2)
+            >= self.frame_duration.saturating_mul(3);
+
+        if timestamp < previous {
+            Some(TimestampAnomaly::NonMonotonic {
+                frame,
+                timestamp,
+                previous,
+            })
+        } else if timestamp == previous {
+            Some(TimestampAnomaly::Duplicate { frame, timestamp })
+        } else if is_gap {
+            Some(TimestampAnomaly::Gap {
+                frame,
+                timestamp,
+                previous,
+            })
+        } else {
+            None
+        }
+    }
+}
+
+impl<R: Read + Seek> Ivf<R> {
+    /// Checks the timestamps of all frames for anomalies, using the frame index.
+    ///
+    /// The frame duration is given in ticks of the timebase. The position of the reader is
+    /// not changed.
+    pub fn timestamp_anomalies(&mut self, frame_duration: u64) -> Result<Vec<TimestampAnomaly>> {
+        let mut checker = TimestampChecker::new(frame_duration);
+        let anomalies = self
+            .index()?
+            .iter()
+            .filter_map(|entry| checker.check(entry.timestamp))
+            .collect();
+        Ok(anomalies)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+
+    #[test]
+    fn convert_timestamps() {
+        let timebase = Timebase::new(1001, 30000);
+        assert_eq!(
+            timebase.to_duration(30),
+            Some(Duration::from_nanos(1_001_000_000))
+        );
+        assert_eq!(
+            timebase.from_duration(Duration::from_millis(1001)),
+            Some(30)
+        );
+        assert_eq!(timebase.rescale(30, Timebase::new(1, 1000)), Some(1001));
+        assert_eq!(Timebase::new(1, 1000).rescale(1001, timebase), Some(30));
+
+        assert_eq!(Timebase::new(1, 0).to_duration(1), None);
+        assert_eq!(
+            Timebase::new(0, 1).from_duration(Duration::from_secs(1)),
+            None
+        );
+        assert_eq!(
+            Timebase::new(u32::MAX, 1).rescale(u64::MAX, Timebase::new(1, u32::MAX)),
+            None
+        );
+    }
+
+    #[test]
+    fn check_timestamps() {
+        let mut checker = TimestampChecker::new(2);
+        let anomalies: Vec<_> = [0, 2, 4, 4, 3, 5, 8, 10]
+            .into_iter()
+            .filter_map(|timestamp| checker.check(timestamp))
+            .collect();
+
+        assert_eq!(
+            anomalies,
+            [
+                TimestampAnomaly::Duplicate {
+                    frame: 3,
+                    timestamp: 4
+                },
+                TimestampAnomaly::NonMonotonic {
+                    frame: 4,
+                    timestamp: 3,
+                    previous: 4
+                },
+                TimestampAnomaly::Gap {
+                    frame: 6,
+                    timestamp: 8,
+                    previous: 5
+                },
+            ]
+        );
+    }
+}
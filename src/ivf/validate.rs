@@ -0,0 +1,298 @@
+//! Validation of the IVF header against the frames of the stream.
+
+use std::{
+    convert::TryFrom,
+    io::{Read, Seek, SeekFrom, Write},
+};
+
+use super::{FourCc, Frame, Ivf, IvfHeader, IvfSlice, IvfWriter, Result, FRAME_HEADER_SIZE};
+use crate::{FrameType, Vp9Parser};
+
+/// A difference between the IVF header and the frames of the stream.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum HeaderWarning {
+    /// The frame count of the header doesn't match the number of frames.
+    FrameCount {
+        /// The frame count of the header.
+        header: u32,
+        /// The number of frames inside the file.
+        actual: usize,
+    },
+    /// The size of the header doesn't match the size of the first key frame.
+    FrameSize {
+        /// The width of the header.
+        header_width: u16,
+        /// The height of the header.
+        header_height: u16,
+        /// The width of the first key frame.
+        width: u16,
+        /// The height of the first key frame.
+        height: u16,
+    },
+    /// A key frame changes the size of the video.
+    SizeChange {
+        /// The number of the IVF frame that contains the key frame.
+        frame: usize,
+        /// The new width.
+        width: u16,
+        /// The new height.
+        height: u16,
+    },
+    /// The stream doesn't contain a key frame, so the frame size can't be checked.
+    MissingKeyFrame,
+    /// A packet couldn't be parsed. The remaining frames of the packet are not checked.
+    CorruptFrame {
+        /// The number of the IVF frame.
+        frame: usize,
+    },
+}
+
+/// The result of comparing the IVF header with the frames of the stream.
+///
+/// The frame sizes are only checked for VP9.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HeaderReport {
+    frame_count: usize,
+    size: Option<(u16, u16)>,
+    max_size: Option<(u16, u16)>,
+    warnings: Vec<HeaderWarning>,
+}
+
+impl HeaderReport {
+    /// The number of frames inside the file.
+    pub fn frame_count(&self) -> usize {
+        self.frame_count
+    }
+
+    /// The width of the first key frame.
+    pub fn width(&self) -> Option<u16> {
+        self.size.map(|(width, _)| width)
+    }
+
+    /// The height of the first key frame.
+    pub fn height(&self) -> Option<u16> {
+        self.size.map(|(_, height)| height)
+    }
+
+    /// The largest width of all frames. Buffers of this width fit every frame.
+    pub fn max_width(&self) -> Option<u16> {
+        self.max_size.map(|(width, _)| width)
+    }
+
+    /// The largest height of all frames. Buffers of this height fit every frame.
+    pub fn max_height(&self) -> Option<u16> {
+        self.max_size.map(|(_, height)| height)
+    }
+
+    /// The differences between the header and the stream.
+    pub fn warnings(&self) -> &[HeaderWarning] {
+        &self.warnings
+    }
+
+    /// Returns true if the header matches the stream.
+    pub fn is_valid(&self) -> bool {
+        self.warnings.is_empty()
+    }
+}
+
+/// Collects the frame count and frame sizes of the packets.
+struct HeaderValidator {
+    parser: Option<Vp9Parser>,
+    frame_count: usize,
+    size: Option<(u16, u16)>,
+    max_size: Option<(u16, u16)>,
+    warnings: Vec<HeaderWarning>,
+}
+
+impl HeaderValidator {
+    fn new(four_cc: FourCc) -> Self {
+        Self {
+            parser: (four_cc == FourCc::Vp9).then(Vp9Parser::default),
+            frame_count: 0,
+            size: None,
+            max_size: None,
+            warnings: Vec::new(),
+        }
+    }
+
+    fn check_packet(&mut self, packet: &[u8]) {
+        let number = self.frame_count;
+        self.frame_count += 1;
+
+        let parser = match self.parser.as_mut() {
+            Some(parser) => parser,
+            None => return,
+        };
+
+        for frame in parser.parse_packet(packet) {
+            let frame = match frame {
+                Ok(frame) => frame,
+                Err(_) => {
+                    self.warnings
+                        .push(HeaderWarning::CorruptFrame { frame: number });
+                    break;
+                }
+            };
+            if frame.show_existing_frame() {
+                continue;
+            }
+
+            let size = (frame.width(), frame.height());
+            let (max_width, max_height) = self.max_size.unwrap_or_default();
+            self.max_size = Some((max_width.max(size.0), max_height.max(size.1)));
+
+            if frame.frame_type() != FrameType::KeyFrame {
+                continue;
+            }
+            match self.size {
+                None => self.size = Some(size),
+                Some(first) if first != size => {
+                    self.warnings.push(HeaderWarning::SizeChange {
+                        frame: number,
+                        width: size.0,
+                        height: size.1,
+                    });
+                }
+                Some(_) => {}
+            }
+        }
+    }
+
+    fn finish(mut self, header: &IvfHeader) -> HeaderReport {
+        let mut warnings = Vec::new();
+        if usize::try_from(header.frame_count).ok() != Some(self.frame_count) {
+            warnings.push(HeaderWarning::FrameCount {
+                header: header.frame_count,
+                actual: self.frame_count,
+            });
+        }
+
+        if self.parser.is_some() {
+            match self.size {
+                Some((width, height)) if (width, height) != (header.width, header.height) => {
+                    warnings.push(HeaderWarning::FrameSize {
+                        header_width: header.width,
+                        header_height: header.height,
+                        width,
+                        height,
+                    });
+                }
+                Some(_) => {}
+                None => warnings.push(HeaderWarning::MissingKeyFrame),
+            }
+        }
+        warnings.append(&mut self.warnings);
+
+        HeaderReport {
+            frame_count: self.frame_count,
+            size: self.size,
+            max_size: self.max_size,
+            warnings,
+        }
+    }
+}
+
+/// Creates a writer with the header corrected by the report.
+///
+/// The size of the first key frame is written, since the IVF header holds the initial size of
+/// the video. Use [`HeaderReport::max_width`] and [`HeaderReport::max_height`] to allocate
+/// buffers for streams that change their size.
+fn corrected_writer<W: Write>(
+    writer: W,
+    header: &IvfHeader,
+    report: &HeaderReport,
+) -> Result<IvfWriter<W>> {
+    let (width, height) = report.size.unwrap_or((header.width, header.height));
+    IvfWriter::with_four_cc(
+        writer,
+        header.four_cc,
+        width,
+        height,
+        header.frame_rate_rate,
+        header.frame_rate_scale,
+    )
+}
+
+impl<R: Read + Seek> Ivf<R> {
+    /// Compares the header with the frames of the file, using the frame index.
+    ///
+    /// For VP9 the packets are parsed to compare the width and height with the key frames.
+    /// The position of the reader is not changed.
+    pub fn validate_header(&mut self) -> Result<HeaderReport> {
+        let mut validator = HeaderValidator::new(self.header.four_cc);
+        self.for_each_frame(|frame| {
+            validator.check_packet(&frame.packet);
+            Ok(())
+        })?;
+        Ok(validator.finish(&self.header))
+    }
+
+    /// Copies the file into the writer with a header that is corrected by
+    /// [`Ivf::validate_header`] and returns the writer and the report.
+    ///
+    /// The header gets the frame count and the size of the first key frame, which is the
+    /// initial size of the video, not the largest size of a stream that changes its size.
+    ///
+    /// A truncated frame at the end of the file is not copied. The position of the reader
+    /// is not changed.
+    pub fn write_corrected<W: Write + Seek>(&mut self, writer: W) -> Result<(W, HeaderReport)> {
+        let report = self.validate_header()?;
+        let mut writer = corrected_writer(writer, &self.header, &report)?;
+        self.for_each_frame(|frame| writer.write_frame(&frame))?;
+        Ok((writer.finish()?, report))
+    }
+
+    /// Reads all frames of the index without changing the position of the reader.
+    fn for_each_frame<F>(&mut self, mut f: F) -> Result<()>
+    where
+        F: FnMut(Frame) -> Result<()>,
+    {
+        let position = self.reader.stream_position()?;
+        let entries = self.index()?.to_vec();
+        for entry in entries {
+            let _ = self
+                .reader
+                .seek(SeekFrom::Start(entry.offset + FRAME_HEADER_SIZE))?;
+            let mut packet = vec![0u8; usize::try_from(entry.size)?];
+            self.reader.read_exact(&mut packet)?;
+            f(Frame {
+                timestamp: entry.timestamp,
+                packet,
+            })?;
+        }
+        let _ = self.reader.seek(SeekFrom::Start(position))?;
+
+        Ok(())
+    }
+}
+
+impl<'a> IvfSlice<'a> {
+    /// Compares the header with the frames of the buffer.
+    ///
+    /// For VP9 the packets are parsed to compare the width and height with the key frames.
+    pub fn validate_header(&self) -> Result<HeaderReport> {
+        let mut validator = HeaderValidator::new(self.header.four_cc);
+        for frame in self.frames() {
+            validator.check_packet(frame?.packet);
+        }
+        Ok(validator.finish(&self.header))
+    }
+
+    /// Copies the buffer into the writer with a header that is corrected by
+    /// [`IvfSlice::validate_header`] and returns the writer and the report.
+    ///
+    /// The header gets the frame count and the size of the first key frame, like
+    /// [`Ivf::write_corrected`].
+    pub fn write_corrected<W: Write + Seek>(&self, writer: W) -> Result<(W, HeaderReport)> {
+        let report = self.validate_header()?;
+        let mut writer = corrected_writer(writer, &self.header, &report)?;
+        for frame in self.frames() {
+            let frame = frame?;
+            writer.write_frame(&Frame {
+                timestamp: frame.timestamp,
+                packet: frame.packet.to_vec(),
+            })?;
+        }
+        Ok((writer.finish()?, report))
+    }
+}
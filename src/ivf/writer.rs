@@ -0,0 +1,143 @@
+//! IVF container writing.
+
+use std::{
+    convert::TryFrom,
+    io::{Seek, SeekFrom, Write},
+};
+
+use super::{FourCc, Frame, Result};
+
+/// Offset of the frame count inside the IVF header.
+const FRAME_COUNT_OFFSET: u64 = 24;
+
+/// Writes VP9 bitstream packets into an IVF container.
+#[derive(Debug, Clone)]
+pub struct IvfWriter<W> {
+    writer: W,
+    frame_count: u32,
+}
+
+impl<W: Write> IvfWriter<W> {
+    /// Creates a new IVF writer for VP9 and writes the header.
+    ///
+    /// The frame count of the header is zero until it's patched by `finish()`, which needs
+    /// a writer that implements `Seek`.
+    pub fn new(
+        writer: W,
+        width: u16,
+        height: u16,
+        frame_rate_rate: u32,
+        frame_rate_scale: u32,
+    ) -> Result<Self> {
+        Self::with_four_cc(
+            writer,
+            FourCc::Vp9,
+            width,
+            height,
+            frame_rate_rate,
+            frame_rate_scale,
+        )
+    }
+
+    /// Creates a new IVF writer for the given codec and writes the header.
+    pub fn with_four_cc(
+        mut writer: W,
+        four_cc: FourCc,
+        width: u16,
+        height: u16,
+        frame_rate_rate: u32,
+        frame_rate_scale: u32,
+    ) -> Result<Self> {
+        let mut header = Vec::with_capacity(32);
+        header.extend_from_slice(&[0x44, 0x4B, 0x49, 0x46]);
+        header.extend_from_slice(&0u16.to_le_bytes());
+        header.extend_from_slice(&32u16.to_le_bytes());
+        header.extend_from_slice(&<[u8; 4]>::from(four_cc));
+        header.extend_from_slice(&width.to_le_bytes());
+        header.extend_from_slice(&height.to_le_bytes());
+        header.extend_from_slice(&frame_rate_rate.to_le_bytes());
+        header.extend_from_slice(&frame_rate_scale.to_le_bytes());
+        header.extend_from_slice(&0u32.to_le_bytes());
+        header.extend_from_slice(&[0u8; 4]);
+        writer.write_all(&header)?;
+
+        Ok(Self {
+            writer,
+            frame_count: 0,
+        })
+    }
+
+    /// Writes a frame.
+    pub fn write_frame(&mut self, frame: &Frame) -> Result<()> {
+        let size = u32::try_from(frame.packet.len())?;
+        self.writer.write_all(&size.to_le_bytes())?;
+        self.writer.write_all(&frame.timestamp.to_le_bytes())?;
+        self.writer.write_all(&frame.packet)?;
+        self.frame_count = self.frame_count.saturating_add(1);
+
+        Ok(())
+    }
+
+    /// The number of frames written so far.
+    pub fn frame_count(&self) -> u32 {
+        self.frame_count
+    }
+
+    /// Destroys the writer and returns the underlying writer without patching the frame count.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write + Seek> IvfWriter<W> {
+    /// Patches the frame count of the header, flushes and returns the underlying writer.
+    ///
+    /// The writer is positioned at the end of the IVF afterwards.
+    pub fn finish(mut self) -> Result<W> {
+        let end = self.writer.stream_position()?;
+        let _ = self.writer.seek(SeekFrom::Start(FRAME_COUNT_OFFSET))?;
+        self.writer.write_all(&self.frame_count.to_le_bytes())?;
+        let _ = self.writer.seek(SeekFrom::Start(end))?;
+        self.writer.flush()?;
+
+        Ok(self.writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::ivf::Ivf;
+
+    #[test]
+    fn write_ivf() {
+        let mut writer = IvfWriter::new(Cursor::new(Vec::new()), 176, 144, 30000, 1000).unwrap();
+        for timestamp in 0..3 {
+            let frame = Frame {
+                timestamp,
+                packet: vec![0x80; usize::try_from(timestamp).unwrap() + 1],
+            };
+            writer.write_frame(&frame).unwrap();
+        }
+        let data = writer.finish().unwrap().into_inner();
+        assert_eq!(data.len(), 32 + 3 * 12 + 6);
+
+        let mut ivf = Ivf::new(Cursor::new(data)).unwrap();
+        assert_eq!(ivf.width(), 176);
+        assert_eq!(ivf.height(), 144);
+        assert_eq!(ivf.frame_rate_rate(), 30000);
+        assert_eq!(ivf.frame_rate_scale(), 1000);
+        assert_eq!(ivf.frame_count(), 3);
+
+        for timestamp in 0..3 {
+            let frame = ivf.read_frame().unwrap().unwrap();
+            assert_eq!(frame.timestamp, timestamp);
+            assert_eq!(frame.packet.len(), usize::try_from(timestamp).unwrap() + 1);
+        }
+        assert!(ivf.read_frame().unwrap().is_none());
+    }
+}
@@ -6,14 +6,27 @@
 #![deny(clippy::unwrap_used)]
 
 //! Provides tools to parse VP9 bitstreams and IVF containers.
-use std::{collections::HashMap, convert::TryInto};
+use std::{collections::HashMap, convert::TryInto, ops::Range, sync::Arc};
 
 use bitreader::BitReader;
 
 pub use error::Vp9ParserError;
-
+pub use frame_header::{
+    ColorConfig, FrameHeader, FrameSize, LoopFilterParams, QuantizationParams, SegmentationParams,
+    TileInfo,
+};
+pub use header::UncompressedHeader;
+pub use limits::{Limit, Limits};
+pub use rewrite::{rewrite_frame, rewrite_packet};
+
+pub mod bsf;
 mod error;
+mod frame_header;
+mod header;
 pub mod ivf;
+mod limits;
+pub mod picture;
+mod rewrite;
 
 type Result<T> = std::result::Result<T, Vp9ParserError>;
 
@@ -151,6 +164,21 @@ impl From<u8> for ColorSpace {
     }
 }
 
+impl From<ColorSpace> for u8 {
+    fn from(c: ColorSpace) -> Self {
+        match c {
+            ColorSpace::Unknown => 0,
+            ColorSpace::Bt601 => 1,
+            ColorSpace::Bt709 => 2,
+            ColorSpace::Smpte170 => 3,
+            ColorSpace::Smpte240 => 4,
+            ColorSpace::Bt2020 => 5,
+            ColorSpace::Reserved => 6,
+            ColorSpace::Rgb => 7,
+        }
+    }
+}
+
 /// Color depth.
 #[derive(Clone, Copy, Debug, Ord, PartialOrd, Eq, PartialEq)]
 pub enum ColorDepth {
@@ -194,6 +222,86 @@ impl From<bool> for ColorRange {
     }
 }
 
+/// Color description as code points defined in ITU-T H.273 (CICP).
+///
+/// These are the values containers like WebM (`Colour`), MP4 (`vpcC`, `colr`)
+/// and AV1 sequence headers use to signal the color space.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct ColorDescription {
+    /// The `colour_primaries` code point.
+    pub color_primaries: u8,
+    /// The `transfer_characteristics` code point.
+    pub transfer_characteristics: u8,
+    /// The `matrix_coefficients` code point.
+    pub matrix_coefficients: u8,
+    /// The `video_full_range_flag`.
+    pub full_range: bool,
+}
+
+impl From<(ColorSpace, ColorRange)> for ColorDescription {
+    /// VP9 only signals the matrix coefficients. Primaries and transfer characteristics
+    /// use the values the respective standard defines. `Unknown` and `Reserved` map to
+    /// "unspecified" (2) for all code points.
+    ///
+    /// BT.2020 defines a different transfer for 12 bit. Without the color depth, the transfer
+    /// for 10 bit and below (14) is used. Convert from `(ColorSpace, ColorRange, ColorDepth)`
+    /// to get 15 for 12 bit.
+    fn from((color_space, color_range): (ColorSpace, ColorRange)) -> Self {
+        let (color_primaries, transfer_characteristics, matrix_coefficients) = match color_space {
+            ColorSpace::Unknown | ColorSpace::Reserved => (2, 2, 2),
+            ColorSpace::Bt601 => (5, 6, 5),
+            ColorSpace::Bt709 => (1, 1, 1),
+            ColorSpace::Smpte170 => (6, 6, 6),
+            ColorSpace::Smpte240 => (7, 7, 7),
+            ColorSpace::Bt2020 => (9, 14, 9),
+            ColorSpace::Rgb => (1, 13, 0),
+        };
+
+        Self {
+            color_primaries,
+            transfer_characteristics,
+            matrix_coefficients,
+            full_range: color_range == ColorRange::FullSwing,
+        }
+    }
+}
+
+impl From<(ColorSpace, ColorRange, ColorDepth)> for ColorDescription {
+    /// Like the conversion from `(ColorSpace, ColorRange)`, but uses the BT.2020 transfer
+    /// for 12 bit (15) for 12 bit frames.
+    fn from((color_space, color_range, color_depth): (ColorSpace, ColorRange, ColorDepth)) -> Self {
+        let mut description = Self::from((color_space, color_range));
+        if color_space == ColorSpace::Bt2020 && color_depth == ColorDepth::Depth12 {
+            description.transfer_characteristics = 15;
+        }
+        description
+    }
+}
+
+impl From<ColorDescription> for (ColorSpace, ColorRange) {
+    /// The color space is derived from the matrix coefficients. Matrices VP9 can't signal map
+    /// to `Unknown`. The identity matrix always uses the full range.
+    fn from(description: ColorDescription) -> Self {
+        let color_space = match description.matrix_coefficients {
+            0 => ColorSpace::Rgb,
+            1 => ColorSpace::Bt709,
+            5 => ColorSpace::Bt601,
+            6 => ColorSpace::Smpte170,
+            7 => ColorSpace::Smpte240,
+            9 => ColorSpace::Bt2020,
+            _ => ColorSpace::Unknown,
+        };
+
+        let color_range = if color_space == ColorSpace::Rgb {
+            ColorRange::FullSwing
+        } else {
+            description.full_range.into()
+        };
+
+        (color_space, color_range)
+    }
+}
+
 /// Type of the interpolation filter.
 #[derive(Clone, Copy, Debug, Ord, PartialOrd, Eq, PartialEq)]
 pub enum InterpolationFilter {
@@ -256,6 +364,18 @@ impl From<u8> for ResetFrameContext {
     }
 }
 
+impl From<ResetFrameContext> for u8 {
+    fn from(r: ResetFrameContext) -> Self {
+        match r {
+            ResetFrameContext::No0 => 0,
+            ResetFrameContext::No1 => 1,
+            ResetFrameContext::SingleReset => 2,
+            ResetFrameContext::FullReset => 3,
+            ResetFrameContext::Unknown => u8::MAX,
+        }
+    }
+}
+
 /// The codec level.
 #[derive(Clone, Copy, Debug, Ord, PartialOrd, Eq, PartialEq)]
 pub enum Level {
@@ -329,7 +449,7 @@ impl Metadata {
 
         let mut features: HashMap<u8, u8> = HashMap::with_capacity(4);
         while pos < data.len() {
-            let (id, value) = Self::read_feature(&mut pos, data);
+            let (id, value) = Self::read_feature(&mut pos, data)?;
             let _ = features.insert(id, value);
         }
 
@@ -368,72 +488,25 @@ impl Metadata {
 
     /// Reads the next feature. Returns the id and the value of the feature.
     #[inline]
-    fn read_feature(pos: &mut usize, data: &[u8]) -> (u8, u8) {
-        let id = data[*pos];
-        let value = data[*pos + 1];
-        *pos += 2;
-        (id, value)
+    fn read_feature(pos: &mut usize, data: &[u8]) -> Result<(u8, u8)> {
+        match data.get(*pos..*pos + 2) {
+            Some(&[id, value]) => {
+                *pos += 2;
+                Ok((id, value))
+            }
+            _ => Err(Vp9ParserError::InvalidMetadata),
+        }
     }
 }
 
-/// A VP9 frame.
+/// A VP9 frame, made of the parsed [`FrameHeader`] and the frame data.
+///
+/// The frame borrows its data from the packet. Use [`Frame::into_owned`] or
+/// [`Vp9Parser::parse_shared_packet`] to get an [`OwnedFrame`].
 #[derive(Clone, Debug)]
 pub struct Frame<'a> {
-    data: &'a [u8],
-    profile: Profile,
-    show_existing_frame: bool,
-    frame_to_show_map_idx: Option<u8>,
-    last_frame_type: FrameType,
-    frame_type: FrameType,
-    show_frame: bool,
-    error_resilient_mode: bool,
-    intra_only: bool,
-    reset_frame_context: ResetFrameContext,
-    ref_frame_indices: [u8; 3],
-    ref_frame_sign_bias: [bool; 4],
-    allow_high_precision_mv: bool,
-    refresh_frame_context: bool,
-    refresh_frame_flags: u8,
-    frame_parallel_decoding_mode: bool,
-    frame_context_idx: u8,
-    uncompressed_header_size: usize,
-    compressed_header_size: usize,
-    tile_size: usize,
-    color_depth: ColorDepth,
-    color_space: ColorSpace,
-    color_range: ColorRange,
-    subsampling_x: bool,
-    subsampling_y: bool,
-    width: u16,
-    height: u16,
-    render_width: u16,
-    render_height: u16,
-    mi_cols: u16,
-    mi_rows: u16,
-    tile_rows_log2: u8,
-    tile_cols_log2: u8,
-    interpolation_filter: InterpolationFilter,
-    loop_filter_level: u8,
-    loop_filter_sharpness: u8,
-    loop_filter_delta_enabled: bool,
-    update_ref_delta: bool,
-    loop_filter_ref_deltas: [i8; 4],
-    update_mode_delta: bool,
-    loop_filter_mode_deltas: [i8; 2],
-    base_q_idx: i32,
-    delta_q_y_dc: i32,
-    delta_q_uv_dc: i32,
-    delta_q_uv_ac: i32,
-    lossless: bool,
-    segmentation_enabled: bool,
-    segmentation_update_map: bool,
-    segment_tree_probs: [u8; 7],
-    segment_pred_probs: [u8; 3],
-    segmentation_temporal_update: bool,
-    segmentation_update_data: bool,
-    segmentation_abs_or_delta_update: bool,
-    segment_feature_enabled: [[bool; 4]; 8],
-    segment_feature_data: [[i16; 4]; 8],
+    header: FrameHeader,
+    data: FrameData<'a>,
 }
 
 impl<'a> Frame<'a> {
@@ -446,109 +519,142 @@ impl<'a> Frame<'a> {
         data: &'a [u8],
     ) -> Self {
         Self {
+            header: FrameHeader {
+                uncompressed_header_size,
+                compressed_header_size,
+                tile_size,
+                ..parser.header
+            },
+            data: FrameData::Borrowed(data),
+        }
+    }
+
+    /// Returns a copy of the frame with the given data.
+    fn with_data<'b>(self, data: FrameData<'b>) -> Frame<'b> {
+        Frame {
+            header: self.header,
             data,
-            profile: parser.profile,
-            show_existing_frame: parser.show_existing_frame,
-            frame_to_show_map_idx: parser.frame_to_show_map_idx,
-            last_frame_type: parser.last_frame_type,
-            frame_type: parser.frame_type,
-            show_frame: parser.show_frame,
-            error_resilient_mode: parser.error_resilient_mode,
-            intra_only: parser.intra_only,
-            reset_frame_context: parser.reset_frame_context,
-            ref_frame_indices: parser.ref_frame_indices,
-            ref_frame_sign_bias: parser.ref_frame_sign_bias,
-            allow_high_precision_mv: parser.allow_high_precision_mv,
-            refresh_frame_context: parser.refresh_frame_context,
-            refresh_frame_flags: parser.refresh_frame_flags,
-            frame_parallel_decoding_mode: parser.frame_parallel_decoding_mode,
-            frame_context_idx: parser.frame_context_idx,
-            uncompressed_header_size,
-            compressed_header_size,
-            tile_size,
-            color_depth: parser.color_depth,
-            color_space: parser.color_space,
-            color_range: parser.color_range,
-            subsampling_x: parser.subsampling_x,
-            subsampling_y: parser.subsampling_y,
-            width: parser.width,
-            height: parser.height,
-            render_width: parser.render_width,
-            render_height: parser.render_height,
-            mi_cols: parser.mi_cols,
-            mi_rows: parser.mi_rows,
-            tile_rows_log2: parser.tile_rows_log2,
-            tile_cols_log2: parser.tile_cols_log2,
-            interpolation_filter: parser.interpolation_filter,
-            loop_filter_level: parser.loop_filter_level,
-            loop_filter_sharpness: parser.loop_filter_sharpness,
-            loop_filter_delta_enabled: parser.loop_filter_delta_enabled,
-            update_ref_delta: parser.update_ref_delta,
-            loop_filter_ref_deltas: parser.loop_filter_ref_deltas,
-            update_mode_delta: parser.update_mode_delta,
-            loop_filter_mode_deltas: parser.loop_filter_mode_deltas,
-            base_q_idx: parser.base_q_idx,
-            delta_q_y_dc: parser.delta_q_y_dc,
-            delta_q_uv_dc: parser.delta_q_uv_dc,
-            delta_q_uv_ac: parser.delta_q_uv_ac,
-            lossless: parser.lossless,
-            segmentation_enabled: parser.segmentation_enabled,
-            segmentation_update_map: parser.segmentation_update_map,
-            segment_tree_probs: parser.segment_tree_probs,
-            segment_pred_probs: parser.segment_pred_probs,
-            segmentation_temporal_update: parser.segmentation_temporal_update,
-            segmentation_update_data: parser.segmentation_update_data,
-            segmentation_abs_or_delta_update: parser.segmentation_abs_or_delta_update,
-            segment_feature_enabled: parser.segment_feature_enabled,
-            segment_feature_data: parser.segment_feature_data,
         }
     }
 
+    /// Converts the frame into a frame that owns its data.
+    ///
+    /// Frames that already share their packet keep it, borrowed frames copy their data.
+    pub fn into_owned(self) -> OwnedFrame {
+        let data = match &self.data {
+            FrameData::Borrowed(data) => FrameData::Shared(Arc::from(*data), 0..data.len()),
+            FrameData::Shared(packet, range) => FrameData::Shared(packet.clone(), range.clone()),
+            #[cfg(feature = "bytes")]
+            FrameData::Bytes(data) => FrameData::Bytes(data.clone()),
+        };
+        self.with_data(data)
+    }
+
+    /// The parsed header of the frame.
+    pub fn header(&self) -> &FrameHeader {
+        &self.header
+    }
+
+    /// Returns a slice into the data of the frame.
+    pub fn data(&self) -> &[u8] {
+        self.data.as_slice()
+    }
+
+    /// The range of the frame inside the packet it shares. Only known for frames of
+    /// [`Vp9Parser::parse_shared_packet`].
+    pub(crate) fn shared_range(&self) -> Option<Range<usize>> {
+        match &self.data {
+            FrameData::Shared(_, range) => Some(range.clone()),
+            _ => None,
+        }
+    }
+
+    /// Returns a slice into the data of the uncompressed header.
+    pub fn uncompressed_header_data(&self) -> &[u8] {
+        self.data()
+            .get(..self.header.uncompressed_header_size)
+            .unwrap_or_default()
+    }
+
     /// Returns a slice into the data of the compressed header.
     pub fn compressed_header_data(&self) -> &[u8] {
-        &self.data[self.uncompressed_header_size
-            ..self.uncompressed_header_size + self.compressed_header_size]
+        self.data()
+            .get(self.header.uncompressed_header_size..self.tile_data_offset())
+            .unwrap_or_default()
     }
 
     /// Returns a slice into the data of the compressed header and tile data.
     pub fn compressed_header_and_tile_data(&self) -> &[u8] {
-        &self.data[self.uncompressed_header_size..self.data.len()]
+        self.data()
+            .get(self.header.uncompressed_header_size..)
+            .unwrap_or_default()
     }
 
     /// Returns a slice into the data of the tile data.
     pub fn tile_data(&self) -> &[u8] {
-        &self.data[self.uncompressed_header_size + self.compressed_header_size..self.data.len()]
+        self.data()
+            .get(self.tile_data_offset()..)
+            .unwrap_or_default()
+    }
+
+    /// Returns the data of the frame as `Bytes`. The data of shared frames is not copied.
+    #[cfg(feature = "bytes")]
+    pub fn data_bytes(&self) -> bytes::Bytes {
+        self.data.slice_bytes(0..self.data().len())
+    }
+
+    /// Returns the data of the compressed header as `Bytes`. The data of shared frames is
+    /// not copied.
+    #[cfg(feature = "bytes")]
+    pub fn compressed_header_bytes(&self) -> bytes::Bytes {
+        self.data
+            .slice_bytes(self.header.uncompressed_header_size..self.tile_data_offset())
+    }
+
+    /// Returns the tile data as `Bytes`. The data of shared frames is not copied.
+    #[cfg(feature = "bytes")]
+    pub fn tile_data_bytes(&self) -> bytes::Bytes {
+        self.data
+            .slice_bytes(self.tile_data_offset()..self.data().len())
+    }
+
+    fn tile_data_offset(&self) -> usize {
+        self.header
+            .uncompressed_header_size
+            .saturating_add(self.header.compressed_header_size)
     }
 
     /// The profile the frame is using.
     pub fn profile(&self) -> Profile {
-        self.profile
+        self.header.profile
     }
 
     /// Indicates that the frame indexed by `frame_to_show_map_idx` is to be displayed.
     /// The frame contains no actual frame data.
+    ///
+    /// See [`FrameHeader`] for the values that are valid for such a frame.
     pub fn show_existing_frame(&self) -> bool {
-        self.show_existing_frame
+        self.header.show_existing_frame
     }
 
     /// Specifies the frame to be displayed. It is only available if `show_existing_frame` is true.
     pub fn frame_to_show_map_idx(&self) -> Option<u8> {
-        self.frame_to_show_map_idx
+        self.header.frame_to_show_map_idx
     }
 
     /// The frame type of the previous frame.
     pub fn last_frame_type(&self) -> FrameType {
-        self.last_frame_type
+        self.header.last_frame_type
     }
 
     /// The frame type of this frame.
     pub fn frame_type(&self) -> FrameType {
-        self.frame_type
+        self.header.frame_type
     }
 
     /// Indicates that the frame is to be displayed.
     pub fn show_frame(&self) -> bool {
-        self.show_frame
+        self.header.show_frame
     }
 
     /// Indicates that error resilient mode is enabled.
@@ -556,7 +662,7 @@ impl<'a> Frame<'a> {
     /// Error resilient mode allows the syntax of a frame to be decoded
     /// independently of previous frames.
     pub fn error_resilient_mode(&self) -> bool {
-        self.error_resilient_mode
+        self.header.error_resilient_mode
     }
 
     /// Indicates that a frame is an `intra-only` frame.
@@ -564,50 +670,50 @@ impl<'a> Frame<'a> {
     /// A key frame is different to an `intra-only` frame even though both only use
     /// intra prediction. The difference is that a key frame fully resets the decoding process.
     pub fn intra_only(&self) -> bool {
-        self.intra_only
+        self.header.intra_only
     }
 
     /// Specifies whether the frame context should be reset to default values.
     pub fn reset_frame_context(&self) -> ResetFrameContext {
-        self.reset_frame_context
+        self.header.reset_frame_context
     }
 
     /// The indices of the used reference frames.
     pub fn ref_frame_indices(&self) -> &[u8; 3] {
-        &self.ref_frame_indices
+        &self.header.ref_frame_indices
     }
 
     /// Last reference frame index.
     pub fn last_ref_frame_index(&self) -> u8 {
-        self.ref_frame_indices[0]
+        self.header.ref_frame_indices[0]
     }
 
     /// Golden reference frame index.
     pub fn golden_ref_frame_index(&self) -> u8 {
-        self.ref_frame_indices[1]
+        self.header.ref_frame_indices[1]
     }
 
     /// Alternate reference frame index.
     pub fn alt_ref_frame_index(&self) -> u8 {
-        self.ref_frame_indices[2]
+        self.header.ref_frame_indices[2]
     }
 
     /// Specifies the intended direction of the motion vector in time for each reference frame.
     pub fn ref_frame_sign_bias(&self) -> &[bool; 4] {
-        &self.ref_frame_sign_bias
+        &self.header.ref_frame_sign_bias
     }
 
     /// Specifies the precision of the motion vectors.
     ///
     /// False = quarter precision, True = eighth precision.
     pub fn allow_high_precision_mv(&self) -> bool {
-        self.allow_high_precision_mv
+        self.header.allow_high_precision_mv
     }
 
     /// Specifies that the probabilities computed for this frame
     /// should be stored for reference by future frames.
     pub fn refresh_frame_context(&self) -> bool {
-        self.refresh_frame_context
+        self.header.refresh_frame_context
     }
 
     /// Contains a bitmask that specifies which reference frame slots
@@ -615,361 +721,330 @@ impl<'a> Frame<'a> {
     ///
     /// First bit = first frame (1). Last bit = last frame (8).
     pub fn refresh_frame_flags(&self) -> u8 {
-        self.refresh_frame_flags
+        self.header.refresh_frame_flags
     }
 
     /// Specifies if parallel decoding mode is activated.
     pub fn frame_parallel_decoding_mode(&self) -> bool {
-        self.frame_parallel_decoding_mode
+        self.header.frame_parallel_decoding_mode
     }
 
     /// Specifies which frame context to use.
     pub fn frame_context_idx(&self) -> u8 {
-        self.frame_context_idx
+        self.header.frame_context_idx
     }
 
     /// The size of the uncompressed header.
     pub fn uncompressed_header_size(&self) -> usize {
-        self.uncompressed_header_size
+        self.header.uncompressed_header_size
     }
 
     /// The size of the uncompressed header.
     pub fn compressed_header_size(&self) -> usize {
-        self.compressed_header_size
+        self.header.compressed_header_size
     }
 
     /// The size of the tile data.
     pub fn tile_size(&self) -> usize {
-        self.tile_size
+        self.header.tile_size
     }
 
     /// The color depth of the frame.
     pub fn color_depth(&self) -> ColorDepth {
-        self.color_depth
+        self.header.color_config.color_depth
     }
 
     /// The color space of the frame.
     pub fn color_space(&self) -> ColorSpace {
-        self.color_space
+        self.header.color_config.color_space
     }
 
     /// The color range of the frame.
     pub fn color_range(&self) -> ColorRange {
-        self.color_range
+        self.header.color_config.color_range
+    }
+
+    /// The color space and color range of the frame as ITU-T H.273 code points.
+    pub fn color_description(&self) -> ColorDescription {
+        self.header.color_config.color_description()
     }
 
     /// The subsampling the frame is using.
     pub fn subsampling(&self) -> Subsampling {
-        if !self.subsampling_x && !self.subsampling_y {
-            Subsampling::Yuv444
-        } else if !self.subsampling_x && self.subsampling_y {
-            Subsampling::Yuv440
-        } else if self.subsampling_x && !self.subsampling_y {
-            Subsampling::Yuv422
-        } else {
-            Subsampling::Yuv420
-        }
+        self.header.color_config.subsampling()
     }
 
     /// Indicates if sub sampling is used along the x axis.
     pub fn subsampling_x(&self) -> bool {
-        self.subsampling_x
+        self.header.color_config.subsampling_x
     }
 
     /// Indicates if sub sampling is used along the y axis.
     pub fn subsampling_y(&self) -> bool {
-        self.subsampling_y
+        self.header.color_config.subsampling_y
     }
 
     /// The width of the frame.
     pub fn width(&self) -> u16 {
-        self.width
+        self.header.frame_size.width
     }
 
     /// The height of the frame.
     pub fn height(&self) -> u16 {
-        self.height
+        self.header.frame_size.height
+    }
+
+    /// The index into `ref_frame_indices` of the reference frame the frame size was taken from.
+    /// `None` if the frame size was coded explicitly.
+    pub fn found_ref(&self) -> Option<u8> {
+        self.header.frame_size.found_ref
+    }
+
+    /// Indicates that the render size was coded explicitly.
+    pub fn render_and_frame_size_different(&self) -> bool {
+        self.header.frame_size.render_and_frame_size_different
     }
 
     /// A hint for the application for the desired width to render.
     pub fn render_width(&self) -> u16 {
-        self.render_width
+        self.header.frame_size.render_width
     }
 
     /// A hint for the application for the desired height to render.
     pub fn render_height(&self) -> u16 {
-        self.render_height
+        self.header.frame_size.render_height
     }
 
     /// A variable holding the vertical location of the block in units of 8x8 pixels.
     pub fn mi_cols(&self) -> u16 {
-        self.mi_cols
+        self.header.frame_size.mi_cols
     }
 
     /// A variable holding the horizontal location of the block in units of 8x8 pixels.
     pub fn mi_rows(&self) -> u16 {
-        self.mi_rows
+        self.header.frame_size.mi_rows
     }
 
     /// The base 2 logarithm of the height of each tile (where the height is measured in units
     /// of 8x8 blocks)
     pub fn tile_rows_log2(&self) -> u8 {
-        self.tile_rows_log2
+        self.header.tile_info.tile_rows_log2
     }
 
     /// The base 2 logarithm of the width of each tile (where the width is measured in units
     /// of 8x8 blocks)
     pub fn tile_cols_log2(&self) -> u8 {
-        self.tile_cols_log2
+        self.header.tile_info.tile_cols_log2
     }
 
     /// The type of filter used in inter prediction.
     pub fn interpolation_filter(&self) -> InterpolationFilter {
-        self.interpolation_filter
+        self.header.interpolation_filter
     }
 
     /// The loop filter strength.
     pub fn loop_filter_level(&self) -> u8 {
-        self.loop_filter_level
+        self.header.loop_filter.loop_filter_level
     }
 
     /// The loop filter sharpness.
     pub fn loop_filter_sharpness(&self) -> u8 {
-        self.loop_filter_sharpness
+        self.header.loop_filter.loop_filter_sharpness
     }
 
     /// Indicates that the filter level depends on the mode and reference frame
     /// used to predict a block.
     pub fn loop_filter_delta_enabled(&self) -> bool {
-        self.loop_filter_delta_enabled
+        self.header.loop_filter.loop_filter_delta_enabled
+    }
+
+    /// Indicates that the bitstream contains updates of the loop filter deltas.
+    pub fn loop_filter_delta_update(&self) -> bool {
+        self.header.loop_filter.loop_filter_delta_update
     }
 
     /// Indicates that the the bitstream contains the syntax element loop_filter_ref_delta.
     pub fn update_ref_delta(&self) -> bool {
-        self.update_ref_delta
+        self.header.loop_filter.update_ref_delta
+    }
+
+    /// Indicates which of the loop_filter_ref_deltas are contained in the bitstream.
+    pub fn ref_deltas_updated(&self) -> &[bool; 4] {
+        &self.header.loop_filter.ref_deltas_updated
     }
 
     /// Contains the adjustment needed for the filter level based on the chosen reference frame.
     pub fn loop_filter_ref_deltas(&self) -> &[i8; 4] {
-        &self.loop_filter_ref_deltas
+        &self.header.loop_filter.loop_filter_ref_deltas
     }
 
     /// Indicates that the the bitstream contains the syntax element loop_filter_mode_deltas.
     pub fn update_mode_delta(&self) -> bool {
-        self.update_mode_delta
+        self.header.loop_filter.update_mode_delta
+    }
+
+    /// Indicates which of the loop_filter_mode_deltas are contained in the bitstream.
+    pub fn mode_deltas_updated(&self) -> &[bool; 2] {
+        &self.header.loop_filter.mode_deltas_updated
     }
 
     /// Contains the adjustment needed for the filter level based on the chosen mode.
     pub fn loop_filter_mode_deltas(&self) -> &[i8; 2] {
-        &self.loop_filter_mode_deltas
+        &self.header.loop_filter.loop_filter_mode_deltas
     }
 
     /// The base frame qindex. This is used for Y AC coefficients and as the base value
     /// for the other quantizers.
     pub fn base_q_idx(&self) -> i32 {
-        self.base_q_idx
+        self.header.quantization.base_q_idx
     }
 
     /// The Y DC quantizer relative to base_q_idx.
     pub fn delta_q_y_dc(&self) -> i32 {
-        self.delta_q_y_dc
+        self.header.quantization.delta_q_y_dc
     }
 
     /// The UV DC quantizer relative to base_q_idx.
     pub fn delta_q_uv_dc(&self) -> i32 {
-        self.delta_q_uv_dc
+        self.header.quantization.delta_q_uv_dc
     }
 
     /// The UV AC quantizer relative to base_q_idx.
     pub fn delta_q_uv_ac(&self) -> i32 {
-        self.delta_q_uv_ac
+        self.header.quantization.delta_q_uv_ac
     }
 
     /// Indicates that the frame is coded using a special 4x4 transform designed
     /// for encoding frames that are bit-identical with the original frames.
     pub fn lossless(&self) -> bool {
-        self.lossless
+        self.header.quantization.lossless
     }
 
     /// Specifies that this frame makes use of the segmentation tool.
     pub fn segmentation_enabled(&self) -> bool {
-        self.segmentation_enabled
+        self.header.segmentation.segmentation_enabled
     }
 
     /// Specifies that the segmentation map should be updated during the decoding of this frame.
     pub fn segmentation_update_map(&self) -> bool {
-        self.segmentation_update_map
+        self.header.segmentation.segmentation_update_map
     }
 
     /// The probability values to be used when decoding segment_id.
     pub fn segment_tree_probs(&self) -> &[u8; 7] {
-        &self.segment_tree_probs
+        &self.header.segmentation.segment_tree_probs
     }
 
     /// The probability values to be used when decoding seg_id_predicted.
     pub fn segment_pred_probs(&self) -> &[u8; 3] {
-        &self.segment_pred_probs
+        &self.header.segmentation.segment_pred_probs
     }
 
     /// Indicates that the updates to the segmentation map are coded
     /// relative to the existing segmentation map.
     pub fn segmentation_temporal_update(&self) -> bool {
-        self.segmentation_temporal_update
+        self.header.segmentation.segmentation_temporal_update
     }
 
     /// Indicates that new parameters are about to be specified for each segment.
     pub fn segmentation_update_data(&self) -> bool {
-        self.segmentation_update_data
+        self.header.segmentation.segmentation_update_data
     }
 
     /// Indicates that the segmentation parameters represent the actual values to be used,
     /// otherwise the segmentation parameters represent adjustments relative to the standard values.
     pub fn segmentation_abs_or_delta_update(&self) -> bool {
-        self.segmentation_abs_or_delta_update
+        self.header.segmentation.segmentation_abs_or_delta_update
     }
 
     /// Indicates that the corresponding feature is used in a segment.
     pub fn segment_feature_enabled(&self) -> &[[bool; 4]; 8] {
-        &self.segment_feature_enabled
+        &self.header.segmentation.segment_feature_enabled
     }
 
     /// Specifies the values of the active features of a segment.
     pub fn segment_feature_data(&self) -> &[[i16; 4]; 8] {
-        &self.segment_feature_data
+        &self.header.segmentation.segment_feature_data
     }
 
     /// Destroys the frame and returns the underlying data buffer.
     pub fn into_vec(self) -> Vec<u8> {
-        let Frame { data, .. } = self;
-        data.to_vec()
+        self.data.as_slice().to_vec()
     }
 }
 
-/// Parses VP9 bitstreams.
+/// A VP9 frame that owns its data, so it can be moved across threads and channels.
+///
+/// The frames of a packet share the packet buffer, so cloning a frame and slicing its
+/// data is cheap.
+pub type OwnedFrame = Frame<'static>;
+
+/// The storage of the data of a frame.
 #[derive(Clone, Debug)]
-pub struct Vp9Parser {
-    ref_frame_sizes: [(u16, u16); 8],
-    profile: Profile,
-    show_existing_frame: bool,
-    frame_to_show_map_idx: Option<u8>,
-    last_frame_type: FrameType,
-    frame_type: FrameType,
-    show_frame: bool,
-    error_resilient_mode: bool,
-    intra_only: bool,
-    reset_frame_context: ResetFrameContext,
-    ref_frame_indices: [u8; 3],
-    ref_frame_sign_bias: [bool; 4],
-    allow_high_precision_mv: bool,
-    refresh_frame_context: bool,
-    refresh_frame_flags: u8,
-    frame_parallel_decoding_mode: bool,
-    frame_context_idx: u8,
-    color_depth: ColorDepth,
-    color_space: ColorSpace,
-    color_range: ColorRange,
-    subsampling_x: bool,
-    subsampling_y: bool,
-    width: u16,
-    height: u16,
-    render_width: u16,
-    render_height: u16,
-    mi_cols: u16,
-    mi_rows: u16,
-    tile_rows_log2: u8,
-    tile_cols_log2: u8,
-    interpolation_filter: InterpolationFilter,
-    loop_filter_level: u8,
-    loop_filter_sharpness: u8,
-    loop_filter_delta_enabled: bool,
-    update_ref_delta: bool,
-    loop_filter_ref_deltas: [i8; 4],
-    update_mode_delta: bool,
-    loop_filter_mode_deltas: [i8; 2],
-    base_q_idx: i32,
-    delta_q_y_dc: i32,
-    delta_q_uv_dc: i32,
-    delta_q_uv_ac: i32,
-    lossless: bool,
-    segmentation_enabled: bool,
-    segmentation_update_map: bool,
-    segment_tree_probs: [u8; 7],
-    segment_pred_probs: [u8; 3],
-    segmentation_temporal_update: bool,
-    segmentation_update_data: bool,
-    segmentation_abs_or_delta_update: bool,
-    segment_feature_enabled: [[bool; 4]; 8],
-    segment_feature_data: [[i16; 4]; 8],
+enum FrameData<'a> {
+    /// Data borrowed from the packet.
+    Borrowed(&'a [u8]),
+    /// The range of the frame inside a shared packet.
+    Shared(Arc<[u8]>, Range<usize>),
+    /// The data of the frame, which can share a `bytes::Bytes` buffer.
+    #[cfg(feature = "bytes")]
+    Bytes(bytes::Bytes),
 }
 
-impl Default for Vp9Parser {
-    fn default() -> Self {
-        Self {
-            ref_frame_sizes: [(0u16, 0u16); 8],
-            show_existing_frame: false,
-            frame_to_show_map_idx: None,
-            profile: Profile::Profile0,
-            last_frame_type: FrameType::NonKeyFrame,
-            frame_type: FrameType::NonKeyFrame,
-            show_frame: false,
-            error_resilient_mode: false,
-            intra_only: false,
-            reset_frame_context: ResetFrameContext::No0,
-            refresh_frame_flags: 0,
-            ref_frame_indices: [0u8; 3],
-            ref_frame_sign_bias: [false; 4],
-            allow_high_precision_mv: false,
-            refresh_frame_context: false,
-            frame_parallel_decoding_mode: true,
-            frame_context_idx: 0,
-            color_depth: ColorDepth::Depth8,
-            color_space: ColorSpace::Unknown,
-            color_range: ColorRange::StudioSwing,
-            subsampling_x: true,
-            subsampling_y: true,
-            width: 0,
-            height: 0,
-            render_width: 0,
-            render_height: 0,
-            mi_cols: 0,
-            mi_rows: 0,
-            tile_rows_log2: 0,
-            tile_cols_log2: 0,
-            interpolation_filter: InterpolationFilter::Eighttap,
-            loop_filter_level: 0,
-            loop_filter_sharpness: 0,
-            loop_filter_delta_enabled: false,
-            update_ref_delta: false,
-            loop_filter_ref_deltas: [1, 0, -1, -1],
-            update_mode_delta: false,
-            loop_filter_mode_deltas: [0, 0],
-            base_q_idx: 0,
-            delta_q_y_dc: 0,
-            delta_q_uv_dc: 0,
-            delta_q_uv_ac: 0,
-            lossless: false,
-            segmentation_enabled: false,
-            segmentation_update_map: false,
-            segment_tree_probs: [0u8; 7],
-            segment_pred_probs: [0u8; 3],
-            segmentation_temporal_update: false,
-            segmentation_update_data: false,
-            segmentation_abs_or_delta_update: false,
-            segment_feature_enabled: [[false; 4]; 8],
-            segment_feature_data: [[0i16; 4]; 8],
+impl<'a> FrameData<'a> {
+    fn as_slice(&self) -> &[u8] {
+        match self {
+            FrameData::Borrowed(data) => data,
+            FrameData::Shared(packet, range) => packet.get(range.clone()).unwrap_or_default(),
+            #[cfg(feature = "bytes")]
+            FrameData::Bytes(data) => data,
+        }
+    }
+
+    /// Returns the given range of the data without copying it if the data is shared.
+    #[cfg(feature = "bytes")]
+    fn slice_bytes(&self, range: Range<usize>) -> bytes::Bytes {
+        let data = match self.as_slice().get(range.clone()) {
+            Some(data) => data,
+            None => return bytes::Bytes::new(),
+        };
+        match self {
+            FrameData::Borrowed(_) => bytes::Bytes::copy_from_slice(data),
+            FrameData::Shared(packet, frame_range) => {
+                let start = frame_range.start + range.start;
+                bytes::Bytes::from_owner(packet.clone()).slice(start..start + data.len())
+            }
+            FrameData::Bytes(bytes) => bytes.slice(range),
         }
     }
 }
 
+/// Parses VP9 bitstreams.
+#[derive(Clone, Debug, Default)]
+pub struct Vp9Parser {
+    limits: Limits,
+    ref_frame_sizes: [(u16, u16); 8],
+    header: FrameHeader,
+}
+
 impl Vp9Parser {
     /// Creates a new parser.
     pub fn new() -> Self {
         Default::default()
     }
 
+    /// Creates a new parser that enforces the given limits.
+    pub fn with_limits(limits: Limits) -> Self {
+        Self {
+            limits,
+            ..Default::default()
+        }
+    }
+
     /// Resets the state of the parser. Used when switching the bitstream or seeking.
+    /// The limits are kept.
     pub fn reset(&mut self) {
-        *self = Vp9Parser::default();
+        *self = Vp9Parser::with_limits(self.limits);
     }
 
     /// Parses a VP9 bitstream packet and returns the encoded frames.
@@ -981,6 +1056,41 @@ impl Vp9Parser {
         Vp9ParserFrameIterator::new(self, packet)
     }
 
+    /// Parses a VP9 bitstream packet like [`Vp9Parser::parse_packet`] and returns frames
+    /// that share the packet buffer.
+    pub fn parse_shared_packet(&mut self, packet: Arc<[u8]>) -> Vec<Result<OwnedFrame>> {
+        self.parse_owned_packet(&packet, |range| FrameData::Shared(packet.clone(), range))
+    }
+
+    /// Parses a VP9 bitstream packet like [`Vp9Parser::parse_packet`] and returns frames
+    /// that share the `Bytes` buffer.
+    #[cfg(feature = "bytes")]
+    pub fn parse_bytes_packet(&mut self, packet: bytes::Bytes) -> Vec<Result<OwnedFrame>> {
+        self.parse_owned_packet(&packet, |range| FrameData::Bytes(packet.slice(range)))
+    }
+
+    /// Parses all frames of the packet and stores their data with the given function, which
+    /// gets the range of the frame inside the packet.
+    fn parse_owned_packet<F>(&mut self, packet: &[u8], data: F) -> Vec<Result<OwnedFrame>>
+    where
+        F: Fn(Range<usize>) -> FrameData<'static>,
+    {
+        let mut frames = self.parse_packet(packet);
+        let mut owned = Vec::new();
+        loop {
+            let start = packet.len() - frames.remaining_len();
+            let frame = match frames.next() {
+                Some(frame) => frame,
+                None => break,
+            };
+            owned.push(frame.map(|frame| {
+                let range = start..start + frame.data().len();
+                frame.with_data(data(range))
+            }));
+        }
+        owned
+    }
+
     fn read_frame_size(
         &self,
         entry_data: &[u8],
@@ -989,25 +1099,22 @@ impl Vp9Parser {
     ) -> Result<usize> {
         // sic! Even though the values inside the uncompressed header are saved in BE,
         // these values are saved in LE.
-        let value: usize = match bytes_size {
-            1 => u8::from_le_bytes(entry_data[index..index + 1].try_into()?).into(),
-            2 => u16::from_le_bytes(entry_data[index * 2..(index * 2) + 2].try_into()?).into(),
-            3 => {
-                let bytes = &entry_data[index * 3..(index * 3) + 3];
-                u32::from_le_bytes([bytes[0], bytes[1], bytes[2], 0x0]).try_into()?
-            }
-            4 => {
-                u32::from_le_bytes(entry_data[index * 4..(index * 4) + 4].try_into()?).try_into()?
-            }
-            _ => {
-                return Err(Vp9ParserError::InvalidFrameSizeByteSize(bytes_size));
-            }
-        };
-        Ok(value)
+        if !(1..=4).contains(&bytes_size) {
+            return Err(Vp9ParserError::InvalidFrameSizeByteSize(bytes_size));
+        }
+
+        let start = index * bytes_size;
+        let bytes = entry_data
+            .get(start..start + bytes_size)
+            .ok_or(Vp9ParserError::InvalidSuperFrameIndex)?;
+
+        let mut value = [0u8; 4];
+        value[..bytes_size].copy_from_slice(bytes);
+        Ok(u32::from_le_bytes(value).try_into()?)
     }
 
     fn parse_frame<'a>(&mut self, data: &'a [u8]) -> Result<Frame<'a>> {
-        let mut br = BitReader::new(&data);
+        let mut br = BitReader::new(data);
 
         let frame_marker = br.read_u8(2)?;
         if frame_marker != 2 {
@@ -1016,96 +1123,115 @@ impl Vp9Parser {
 
         let profile_low_bit = br.read_u8(1)?;
         let profile_high_bit = br.read_u8(1)?;
-        self.profile = ((profile_high_bit << 1) + profile_low_bit).into();
-        if self.profile == Profile::Profile3 {
+        self.header.profile = ((profile_high_bit << 1) + profile_low_bit).into();
+        if self.header.profile == Profile::Profile3 {
             let _reserved_zero = br.read_u8(1)?;
         }
 
-        self.show_existing_frame = br.read_bool()?;
+        self.header.show_existing_frame = br.read_bool()?;
 
-        if self.show_existing_frame {
-            self.frame_to_show_map_idx = Some(br.read_u8(3)?);
-            self.refresh_frame_flags = 0;
-            self.loop_filter_level = 0;
+        if self.header.show_existing_frame {
+            self.header.frame_to_show_map_idx = Some(br.read_u8(3)?);
+            self.header.show_frame = true;
+            self.header.refresh_frame_flags = 0;
+            self.header.loop_filter.loop_filter_level = 0;
 
-            let frame = Frame::new(self, 0, 0, 0, &[]);
+            self.trailing_bits(&mut br)?;
+            let uncompressed_header_size: usize = (br.position() / 8).try_into()?;
+
+            let frame = Frame::new(
+                self,
+                uncompressed_header_size,
+                0,
+                0,
+                &data[..uncompressed_header_size],
+            );
             return Ok(frame);
         } else {
-            self.frame_to_show_map_idx = None;
+            self.header.frame_to_show_map_idx = None;
         }
 
-        self.last_frame_type = self.frame_type;
-        self.frame_type = br.read_bool()?.into();
+        self.header.last_frame_type = self.header.frame_type;
+        self.header.frame_type = br.read_bool()?.into();
 
-        self.show_frame = br.read_bool()?;
-        self.error_resilient_mode = br.read_bool()?;
+        self.header.show_frame = br.read_bool()?;
+        self.header.error_resilient_mode = br.read_bool()?;
 
-        if self.frame_type == FrameType::KeyFrame {
+        if self.header.frame_type == FrameType::KeyFrame {
+            self.header.intra_only = false;
             self.frame_sync_code(&mut br)?;
             self.color_config(&mut br)?;
             self.frame_size(&mut br)?;
             self.render_size(&mut br)?;
-            self.refresh_frame_flags = 0xFF;
+            self.header.refresh_frame_flags = 0xFF;
         } else {
-            if !self.show_frame {
-                self.intra_only = br.read_bool()?
+            if !self.header.show_frame {
+                self.header.intra_only = br.read_bool()?
             } else {
-                self.intra_only = false;
+                self.header.intra_only = false;
             };
 
-            if !self.error_resilient_mode {
-                self.reset_frame_context = br.read_u8(2)?.into()
+            if !self.header.error_resilient_mode {
+                self.header.reset_frame_context = br.read_u8(2)?.into()
             } else {
-                self.reset_frame_context = ResetFrameContext::No0;
+                self.header.reset_frame_context = ResetFrameContext::No0;
             };
 
-            if self.intra_only {
+            if self.header.intra_only {
                 self.frame_sync_code(&mut br)?;
-                if self.profile > Profile::Profile0 {
+                if self.header.profile > Profile::Profile0 {
                     self.color_config(&mut br)?;
                 } else {
-                    self.color_depth = ColorDepth::Depth8;
-                    self.color_space = ColorSpace::Bt601;
-                    self.subsampling_x = true;
-                    self.subsampling_y = true;
+                    self.header.color_config.color_depth = ColorDepth::Depth8;
+                    self.header.color_config.color_space = ColorSpace::Bt601;
+                    self.header.color_config.subsampling_x = true;
+                    self.header.color_config.subsampling_y = true;
                 }
-                self.refresh_frame_flags = br.read_u8(8)?;
+                self.header.refresh_frame_flags = br.read_u8(8)?;
                 self.frame_size(&mut br)?;
                 self.render_size(&mut br)?;
             } else {
-                self.refresh_frame_flags = br.read_u8(8)?;
+                self.header.refresh_frame_flags = br.read_u8(8)?;
                 for i in 0..3 {
-                    self.ref_frame_indices[i] = br.read_u8(3)?;
-                    self.ref_frame_sign_bias[LAST_FRAME + i] = br.read_bool()?;
+                    self.header.ref_frame_indices[i] = br.read_u8(3)?;
+                    self.header.ref_frame_sign_bias[LAST_FRAME + i] = br.read_bool()?;
                 }
                 self.frame_size_with_refs(&mut br)?;
-                self.allow_high_precision_mv = br.read_bool()?;
+                self.header.allow_high_precision_mv = br.read_bool()?;
                 self.read_interpolation_filter(&mut br)?;
             }
         }
 
-        if !self.error_resilient_mode {
-            self.refresh_frame_context = br.read_bool()?;
-            self.frame_parallel_decoding_mode = br.read_bool()?;
+        if !self.header.error_resilient_mode {
+            self.header.refresh_frame_context = br.read_bool()?;
+            self.header.frame_parallel_decoding_mode = br.read_bool()?;
         } else {
-            self.refresh_frame_context = false;
-            self.frame_parallel_decoding_mode = false;
+            self.header.refresh_frame_context = false;
+            self.header.frame_parallel_decoding_mode = false;
         };
 
-        self.frame_context_idx = br.read_u8(2)?;
+        self.header.frame_context_idx = br.read_u8(2)?;
 
-        if self.intra_only || self.error_resilient_mode {
-            self.frame_context_idx = 0
+        if self.header.intra_only || self.header.error_resilient_mode {
+            self.header.frame_context_idx = 0
         }
 
-        if self.frame_type == FrameType::KeyFrame || self.error_resilient_mode || self.intra_only {
+        if self.header.frame_type == FrameType::KeyFrame
+            || self.header.error_resilient_mode
+            || self.header.intra_only
+        {
             // Reset the loop filter deltas.
-            self.loop_filter_ref_deltas[INTRA_FRAME] = 1;
-            self.loop_filter_ref_deltas[LAST_FRAME] = 0;
-            self.loop_filter_ref_deltas[GOLDEN_FRAME] = -1;
-            self.loop_filter_ref_deltas[ALTREF_FRAME] = -1;
-            self.loop_filter_mode_deltas[0] = 0;
-            self.loop_filter_mode_deltas[1] = 0;
+            self.header.loop_filter.loop_filter_ref_deltas[INTRA_FRAME] = 1;
+            self.header.loop_filter.loop_filter_ref_deltas[LAST_FRAME] = 0;
+            self.header.loop_filter.loop_filter_ref_deltas[GOLDEN_FRAME] = -1;
+            self.header.loop_filter.loop_filter_ref_deltas[ALTREF_FRAME] = -1;
+            self.header.loop_filter.loop_filter_mode_deltas[0] = 0;
+            self.header.loop_filter.loop_filter_mode_deltas[1] = 0;
+
+            // Reset the segmentation features.
+            self.header.segmentation.segmentation_abs_or_delta_update = false;
+            self.header.segmentation.segment_feature_enabled = [[false; 4]; 8];
+            self.header.segmentation.segment_feature_data = [[0i16; 4]; 8];
         }
         self.loop_filter_params(&mut br)?;
 
@@ -1117,8 +1243,10 @@ impl Vp9Parser {
         self.trailing_bits(&mut br)?;
         let uncompressed_header_size: usize = (br.position() / 8).try_into()?;
 
-        let size = data.len();
-        let tile_size = size - (uncompressed_header_size + compressed_header_size);
+        let tile_size = data
+            .len()
+            .checked_sub(uncompressed_header_size + compressed_header_size)
+            .ok_or(Vp9ParserError::InvalidHeaderSize)?;
 
         let frame = Frame::new(
             self,
@@ -1135,9 +1263,9 @@ impl Vp9Parser {
 
     // Implements spec "8.10 Reference frame update process".
     fn refresh_ref_frames(&mut self) {
-        let flags = self.refresh_frame_flags;
-        let new_width = self.width;
-        let new_height = self.height;
+        let flags = self.header.refresh_frame_flags;
+        let new_width = self.header.frame_size.width;
+        let new_height = self.header.frame_size.height;
         self.ref_frame_sizes
             .iter_mut()
             .enumerate()
@@ -1154,7 +1282,7 @@ impl Vp9Parser {
         let frame_sync_byte_1 = br.read_u8(8)?;
         let frame_sync_byte_2 = br.read_u8(8)?;
 
-        if frame_sync_byte_0 != 0x49 && frame_sync_byte_1 != 0x83 && frame_sync_byte_2 != 0x42 {
+        if frame_sync_byte_0 != 0x49 || frame_sync_byte_1 != 0x83 || frame_sync_byte_2 != 0x42 {
             return Err(Vp9ParserError::InvalidSyncByte);
         }
 
@@ -1162,35 +1290,37 @@ impl Vp9Parser {
     }
 
     fn color_config(&mut self, br: &mut BitReader) -> Result<()> {
-        if self.profile >= Profile::Profile2 {
+        if self.header.profile >= Profile::Profile2 {
             let ten_or_twelve_bit = br.read_bool()?;
             if ten_or_twelve_bit {
-                self.color_depth = ColorDepth::Depth12;
+                self.header.color_config.color_depth = ColorDepth::Depth12;
             } else {
-                self.color_depth = ColorDepth::Depth10;
+                self.header.color_config.color_depth = ColorDepth::Depth10;
             }
         } else {
-            self.color_depth = ColorDepth::Depth8;
+            self.header.color_config.color_depth = ColorDepth::Depth8;
         };
 
-        self.color_space = br.read_u8(3)?.into();
+        self.header.color_config.color_space = br.read_u8(3)?.into();
 
-        if self.color_space == ColorSpace::Rgb {
-            self.color_range = ColorRange::FullSwing;
-            if self.profile == Profile::Profile1 || self.profile == Profile::Profile3 {
-                self.subsampling_x = false;
-                self.subsampling_y = false;
+        if self.header.color_config.color_space == ColorSpace::Rgb {
+            self.header.color_config.color_range = ColorRange::FullSwing;
+            if self.header.profile == Profile::Profile1 || self.header.profile == Profile::Profile3
+            {
+                self.header.color_config.subsampling_x = false;
+                self.header.color_config.subsampling_y = false;
                 let _reserved_zero = br.read_u8(1)?;
             }
         } else {
-            self.color_range = br.read_bool()?.into();
-            if self.profile == Profile::Profile1 || self.profile == Profile::Profile3 {
-                self.subsampling_x = br.read_bool()?;
-                self.subsampling_y = br.read_bool()?;
+            self.header.color_config.color_range = br.read_bool()?.into();
+            if self.header.profile == Profile::Profile1 || self.header.profile == Profile::Profile3
+            {
+                self.header.color_config.subsampling_x = br.read_bool()?;
+                self.header.color_config.subsampling_y = br.read_bool()?;
                 let _reserved_zero = br.read_u8(1)?;
             } else {
-                self.subsampling_x = true;
-                self.subsampling_y = true;
+                self.header.color_config.subsampling_x = true;
+                self.header.color_config.subsampling_y = true;
             }
         }
 
@@ -1198,26 +1328,27 @@ impl Vp9Parser {
     }
 
     fn frame_size(&mut self, br: &mut BitReader) -> Result<()> {
+        self.header.frame_size.found_ref = None;
         let frame_width_minus_1 = br.read_u16(16)?;
         let frame_height_minus_1 = br.read_u16(16)?;
-        self.width = frame_width_minus_1 + 1;
-        self.height = frame_height_minus_1 + 1;
-
-        self.compute_image_size();
+        self.header.frame_size.width = (u32::from(frame_width_minus_1) + 1).try_into()?;
+        self.header.frame_size.height = (u32::from(frame_height_minus_1) + 1).try_into()?;
 
-        Ok(())
+        self.compute_image_size()
     }
 
     fn render_size(&mut self, br: &mut BitReader) -> Result<()> {
-        let render_and_frame_size_different = br.read_bool()?;
-        if render_and_frame_size_different {
+        self.header.frame_size.render_and_frame_size_different = br.read_bool()?;
+        if self.header.frame_size.render_and_frame_size_different {
             let render_width_minus_1 = br.read_u16(16)?;
             let render_height_minus_1 = br.read_u16(16)?;
-            self.render_width = render_width_minus_1 + 1;
-            self.render_height = render_height_minus_1 + 1;
+            self.header.frame_size.render_width =
+                (u32::from(render_width_minus_1) + 1).try_into()?;
+            self.header.frame_size.render_height =
+                (u32::from(render_height_minus_1) + 1).try_into()?;
         } else {
-            self.render_width = self.width;
-            self.render_height = self.height;
+            self.header.frame_size.render_width = self.header.frame_size.width;
+            self.header.frame_size.render_height = self.header.frame_size.height;
         }
 
         Ok(())
@@ -1230,11 +1361,12 @@ impl Vp9Parser {
             if found_ref {
                 let sizes = *self
                     .ref_frame_sizes
-                    .get(usize::from(self.ref_frame_indices[i]))
+                    .get(usize::from(self.header.ref_frame_indices[i]))
                     .ok_or(Vp9ParserError::InvalidRefFrameIndex)?;
 
-                self.width = sizes.0;
-                self.height = sizes.1;
+                self.header.frame_size.width = sizes.0;
+                self.header.frame_size.height = sizes.1;
+                self.header.frame_size.found_ref = Some(i.try_into()?);
                 break;
             }
         }
@@ -1242,7 +1374,7 @@ impl Vp9Parser {
         if !found_ref {
             self.frame_size(br)?;
         } else {
-            self.compute_image_size();
+            self.compute_image_size()?;
         }
 
         self.render_size(br)?;
@@ -1250,18 +1382,22 @@ impl Vp9Parser {
         Ok(())
     }
 
-    fn compute_image_size(&mut self) {
-        self.mi_cols = (self.width + 7) >> 3;
-        self.mi_rows = (self.height + 7) >> 3;
+    fn compute_image_size(&mut self) -> Result<()> {
+        self.limits
+            .check_frame_size(self.header.frame_size.width, self.header.frame_size.height)?;
+        self.header.frame_size.mi_cols = calc_mi_units(self.header.frame_size.width);
+        self.header.frame_size.mi_rows = calc_mi_units(self.header.frame_size.height);
+
+        Ok(())
     }
 
     fn read_interpolation_filter(&mut self, br: &mut BitReader) -> Result<()> {
         let is_filter_switchable = br.read_bool()?;
         if is_filter_switchable {
-            self.interpolation_filter = InterpolationFilter::Switchable;
+            self.header.interpolation_filter = InterpolationFilter::Switchable;
         } else {
             let raw_interpolation_filter = br.read_u8(2)?;
-            self.interpolation_filter = match raw_interpolation_filter {
+            self.header.interpolation_filter = match raw_interpolation_filter {
                 0 => InterpolationFilter::EighttapSmooth,
                 1 => InterpolationFilter::Eighttap,
                 2 => InterpolationFilter::EighttapSharp,
@@ -1274,41 +1410,56 @@ impl Vp9Parser {
     }
 
     fn loop_filter_params(&mut self, br: &mut BitReader) -> Result<()> {
-        self.loop_filter_level = br.read_u8(6)?;
-        self.loop_filter_sharpness = br.read_u8(3)?;
-        self.loop_filter_delta_enabled = br.read_bool()?;
-
-        if self.loop_filter_delta_enabled {
-            let loop_filter_delta_update = br.read_bool()?;
-            if loop_filter_delta_update {
-                for delta in self.loop_filter_ref_deltas.iter_mut() {
-                    let update_ref_delta = br.read_bool()?;
-                    if update_ref_delta {
+        let loop_filter = &mut self.header.loop_filter;
+        loop_filter.loop_filter_level = br.read_u8(6)?;
+        loop_filter.loop_filter_sharpness = br.read_u8(3)?;
+        loop_filter.loop_filter_delta_enabled = br.read_bool()?;
+        loop_filter.loop_filter_delta_update = false;
+        loop_filter.ref_deltas_updated = [false; 4];
+        loop_filter.mode_deltas_updated = [false; 2];
+
+        if loop_filter.loop_filter_delta_enabled {
+            loop_filter.loop_filter_delta_update = br.read_bool()?;
+            if loop_filter.loop_filter_delta_update {
+                for (delta, updated) in loop_filter
+                    .loop_filter_ref_deltas
+                    .iter_mut()
+                    .zip(loop_filter.ref_deltas_updated.iter_mut())
+                {
+                    *updated = br.read_bool()?;
+                    if *updated {
                         *delta = br.read_inverse_i8(6)?;
                     }
                 }
 
-                for mode in self.loop_filter_mode_deltas.iter_mut() {
-                    let update_mode_delta = br.read_bool()?;
-                    if update_mode_delta {
+                for (mode, updated) in loop_filter
+                    .loop_filter_mode_deltas
+                    .iter_mut()
+                    .zip(loop_filter.mode_deltas_updated.iter_mut())
+                {
+                    *updated = br.read_bool()?;
+                    if *updated {
                         *mode = br.read_inverse_i8(6)?;
                     }
                 }
             }
         }
 
+        loop_filter.update_ref_delta = loop_filter.ref_deltas_updated.contains(&true);
+        loop_filter.update_mode_delta = loop_filter.mode_deltas_updated.contains(&true);
+
         Ok(())
     }
 
     fn quantization_params(&mut self, br: &mut BitReader) -> Result<()> {
-        self.base_q_idx = (br.read_u8(8)?).into();
-        self.delta_q_y_dc = self.read_delta_q(br)?;
-        self.delta_q_uv_dc = self.read_delta_q(br)?;
-        self.delta_q_uv_ac = self.read_delta_q(br)?;
-        self.lossless = self.base_q_idx == 0
-            && self.delta_q_y_dc == 0
-            && self.delta_q_uv_dc == 0
-            && self.delta_q_uv_ac == 0;
+        self.header.quantization.base_q_idx = (br.read_u8(8)?).into();
+        self.header.quantization.delta_q_y_dc = self.read_delta_q(br)?;
+        self.header.quantization.delta_q_uv_dc = self.read_delta_q(br)?;
+        self.header.quantization.delta_q_uv_ac = self.read_delta_q(br)?;
+        self.header.quantization.lossless = self.header.quantization.base_q_idx == 0
+            && self.header.quantization.delta_q_y_dc == 0
+            && self.header.quantization.delta_q_uv_dc == 0
+            && self.header.quantization.delta_q_uv_ac == 0;
 
         Ok(())
     }
@@ -1324,17 +1475,22 @@ impl Vp9Parser {
     }
 
     fn segmentation_params(&mut self, br: &mut BitReader) -> Result<()> {
-        self.segmentation_enabled = br.read_bool()?;
-        if self.segmentation_enabled {
-            self.segmentation_update_map = br.read_bool()?;
-            if self.segmentation_update_map {
-                for prob in self.segment_tree_probs.iter_mut() {
+        let segmentation = &mut self.header.segmentation;
+        segmentation.segmentation_update_map = false;
+        segmentation.segmentation_temporal_update = false;
+        segmentation.segmentation_update_data = false;
+
+        segmentation.segmentation_enabled = br.read_bool()?;
+        if segmentation.segmentation_enabled {
+            segmentation.segmentation_update_map = br.read_bool()?;
+            if segmentation.segmentation_update_map {
+                for prob in segmentation.segment_tree_probs.iter_mut() {
                     *prob = Self::read_prob(br)?;
                 }
 
-                self.segmentation_temporal_update = br.read_bool()?;
-                for prob in self.segment_pred_probs.iter_mut() {
-                    *prob = if self.segmentation_temporal_update {
+                segmentation.segmentation_temporal_update = br.read_bool()?;
+                for prob in segmentation.segment_pred_probs.iter_mut() {
+                    *prob = if segmentation.segmentation_temporal_update {
                         Self::read_prob(br)?
                     } else {
                         255
@@ -1342,24 +1498,28 @@ impl Vp9Parser {
                 }
             }
 
-            self.segmentation_update_data = br.read_bool()?;
-            if self.segmentation_update_data {
-                self.segmentation_abs_or_delta_update = br.read_bool()?;
+            segmentation.segmentation_update_data = br.read_bool()?;
+            if segmentation.segmentation_update_data {
+                segmentation.segmentation_abs_or_delta_update = br.read_bool()?;
+                segmentation.segment_feature_data = [[0i16; 4]; 8];
                 for i in 0..MAX_SEGMENTS {
-                    self.segment_feature_enabled[i][SEG_LVL_ALT_Q] = br.read_bool()?;
-                    if self.segment_feature_enabled[i][SEG_LVL_ALT_Q] {
-                        self.segment_feature_data[i][SEG_LVL_ALT_Q] = br.read_inverse_i16(8)?;
+                    segmentation.segment_feature_enabled[i][SEG_LVL_ALT_Q] = br.read_bool()?;
+                    if segmentation.segment_feature_enabled[i][SEG_LVL_ALT_Q] {
+                        segmentation.segment_feature_data[i][SEG_LVL_ALT_Q] =
+                            br.read_inverse_i16(8)?;
                     };
-                    self.segment_feature_enabled[i][SEG_LVL_ALT_L] = br.read_bool()?;
-                    if self.segment_feature_enabled[i][SEG_LVL_ALT_L] {
-                        self.segment_feature_data[i][SEG_LVL_ALT_L] = br.read_inverse_i16(6)?;
+                    segmentation.segment_feature_enabled[i][SEG_LVL_ALT_L] = br.read_bool()?;
+                    if segmentation.segment_feature_enabled[i][SEG_LVL_ALT_L] {
+                        segmentation.segment_feature_data[i][SEG_LVL_ALT_L] =
+                            br.read_inverse_i16(6)?;
                     };
-                    self.segment_feature_enabled[i][SEG_LVL_REF_FRAME] = br.read_bool()?;
-                    if self.segment_feature_enabled[i][SEG_LVL_REF_FRAME] {
-                        self.segment_feature_data[i][SEG_LVL_REF_FRAME] = br.read_inverse_i16(2)?;
+                    segmentation.segment_feature_enabled[i][SEG_LVL_REF_FRAME] = br.read_bool()?;
+                    if segmentation.segment_feature_enabled[i][SEG_LVL_REF_FRAME] {
+                        segmentation.segment_feature_data[i][SEG_LVL_REF_FRAME] =
+                            br.read_u8(2)?.into();
                     };
-                    self.segment_feature_enabled[i][SEG_LVL_SKIP] = br.read_bool()?;
-                    self.segment_feature_data[i][SEG_LVL_SKIP] = 0;
+                    segmentation.segment_feature_enabled[i][SEG_LVL_SKIP] = br.read_bool()?;
+                    segmentation.segment_feature_data[i][SEG_LVL_SKIP] = 0;
                 }
             }
         }
@@ -1378,47 +1538,36 @@ impl Vp9Parser {
     }
 
     fn tile_info(&mut self, br: &mut BitReader) -> Result<()> {
-        let min_log2_tile_cols = self.calc_min_log2_tile_cols()?;
-        let max_log2_tile_cols = self.calc_max_log2_tile_cols()?;
-        self.tile_rows_log2 = min_log2_tile_cols;
-        while self.tile_rows_log2 < max_log2_tile_cols {
+        let min_log2_tile_cols = calc_min_log2_tile_cols(self.header.frame_size.mi_cols);
+        let max_log2_tile_cols = calc_max_log2_tile_cols(self.header.frame_size.mi_cols);
+        self.header.tile_info.tile_cols_log2 = min_log2_tile_cols;
+        while self.header.tile_info.tile_cols_log2 < max_log2_tile_cols {
             let increment_tile_cols_log2 = br.read_bool()?;
             if increment_tile_cols_log2 {
-                self.tile_cols_log2 = self.tile_cols_log2.saturating_add(1);
+                self.header.tile_info.tile_cols_log2 =
+                    self.header.tile_info.tile_cols_log2.saturating_add(1);
             } else {
                 break;
             }
         }
-        self.tile_rows_log2 = br.read_u8(1)?;
-        if self.tile_rows_log2 == 1 {
+        self.header.tile_info.tile_rows_log2 = br.read_u8(1)?;
+        if self.header.tile_info.tile_rows_log2 == 1 {
             let increment_tile_rows_log2 = br.read_u8(1)?;
-            self.tile_rows_log2 += increment_tile_rows_log2;
+            self.header.tile_info.tile_rows_log2 += increment_tile_rows_log2;
         }
 
-        Ok(())
-    }
-
-    fn calc_min_log2_tile_cols(&self) -> Result<u8> {
-        let mut min_log2 = 0;
-        let sb64_cols: u8 = ((self.mi_cols + 7) >> 3).try_into()?;
-        while (MAX_TILE_WIDTH_B64 << min_log2) < sb64_cols {
-            min_log2 += 1;
+        let tiles =
+            1usize << (self.header.tile_info.tile_cols_log2 + self.header.tile_info.tile_rows_log2);
+        if tiles > self.limits.max_tiles {
+            return Err(Limit::Tiles(tiles).into());
         }
-        Ok(min_log2)
-    }
 
-    fn calc_max_log2_tile_cols(&self) -> Result<u8> {
-        let mut max_log2 = 1;
-        let sb64_cols: u8 = ((self.mi_cols + 7) >> 3).try_into()?;
-        while (sb64_cols >> max_log2) >= MIN_TILE_WIDTH_B64 {
-            max_log2 += 1;
-        }
-        Ok(max_log2 - 1)
+        Ok(())
     }
 
     // Aligns the reader to the next byte offset.
     fn trailing_bits(&self, br: &mut BitReader) -> Result<()> {
-        while br.is_aligned(1) {
+        while !br.is_aligned(1) {
             let zero_bit = br.read_bool()?;
             if zero_bit {
                 return Err(Vp9ParserError::InvalidPadding);
@@ -1429,6 +1578,29 @@ impl Vp9Parser {
     }
 }
 
+// Number of 8x8 mode info units needed to cover the given number of pixels.
+fn calc_mi_units(size: u16) -> u16 {
+    size.div_ceil(8)
+}
+
+fn calc_min_log2_tile_cols(mi_cols: u16) -> u8 {
+    let mut min_log2 = 0;
+    let sb64_cols = (mi_cols + 7) >> 3;
+    while (u16::from(MAX_TILE_WIDTH_B64) << min_log2) < sb64_cols {
+        min_log2 += 1;
+    }
+    min_log2
+}
+
+fn calc_max_log2_tile_cols(mi_cols: u16) -> u8 {
+    let mut max_log2 = 1;
+    let sb64_cols = (mi_cols + 7) >> 3;
+    while (sb64_cols >> max_log2) >= u16::from(MIN_TILE_WIDTH_B64) {
+        max_log2 += 1;
+    }
+    max_log2 - 1
+}
+
 /// A VP9 frame iterator
 pub struct Vp9ParserFrameIterator<'a, 'b> {
     parser: &'b mut Vp9Parser,
@@ -1437,6 +1609,30 @@ pub struct Vp9ParserFrameIterator<'a, 'b> {
     frame_index: usize,
 }
 
+impl<'a, 'b> Vp9ParserFrameIterator<'a, 'b> {
+    /// Indicates that the packet is a super frame.
+    pub fn is_super_frame(&self) -> bool {
+        self.super_frame.is_some()
+    }
+
+    /// The number of bytes of the packet that have not been consumed yet.
+    fn remaining_len(&self) -> usize {
+        self.packet.len()
+    }
+
+    fn check_limits(&self) -> std::result::Result<(), Limit> {
+        let limits = &self.parser.limits;
+        limits.check_packet_size(self.packet.len())?;
+        if let Some(super_frame) = self.super_frame.as_ref() {
+            if super_frame.frame_count > limits.max_frames_per_super_frame {
+                return Err(Limit::FramesPerSuperFrame(super_frame.frame_count));
+            }
+        }
+
+        Ok(())
+    }
+}
+
 struct Vp9SuperFrame<'a> {
     entry_data: &'a [u8],
     bytes_size: usize,
@@ -1450,8 +1646,7 @@ impl<'a, 'b> Vp9ParserFrameIterator<'a, 'b> {
         }
 
         // Test for a super frame.
-        let last_byte_index = packet.len() - 1;
-        let last_byte = packet[last_byte_index];
+        let last_byte = packet.last().copied().unwrap_or_default();
 
         if last_byte & 0b1110_0000 == 0b1100_0000 {
             let bytes_per_framesize_minus_1 = (last_byte & 0b11000) >> 3;
@@ -1459,31 +1654,30 @@ impl<'a, 'b> Vp9ParserFrameIterator<'a, 'b> {
             let bytes_size: usize = (bytes_per_framesize_minus_1 + 1).into();
             let frame_count: usize = (frames_in_superframe_minus_1 + 1).into();
             let index_size = 2 + frame_count * bytes_size;
-            let first_byte_index = packet.len() - index_size;
-            let first_byte = packet[first_byte_index];
-
-            let is_super_frame = first_byte == last_byte;
-
-            if is_super_frame {
-                let index_start = first_byte_index + 1;
-                let entry_size = frame_count * bytes_size;
-
-                let entry_data = &packet[index_start..index_start + entry_size];
-                return Vp9ParserFrameIterator {
-                    parser,
-                    packet,
-                    frame_index: 0,
-                    super_frame: Some(Vp9SuperFrame {
-                        entry_data,
-                        frame_count,
-                        bytes_size,
-                    }),
-                };
+
+            // A packet too small to hold the index is parsed as a normal frame.
+            if let Some(first_byte_index) = packet.len().checked_sub(index_size) {
+                let index = &packet[first_byte_index..];
+                let is_super_frame = index.first() == Some(&last_byte);
+
+                if is_super_frame {
+                    let entry_data = &index[1..index.len() - 1];
+                    return Vp9ParserFrameIterator {
+                        parser,
+                        packet,
+                        frame_index: 0,
+                        super_frame: Some(Vp9SuperFrame {
+                            entry_data,
+                            frame_count,
+                            bytes_size,
+                        }),
+                    };
+                }
             }
         }
 
         // Normal frame.
-        return Vp9ParserFrameIterator {
+        Vp9ParserFrameIterator {
             parser,
             packet,
             frame_index: 0,
@@ -1500,6 +1694,13 @@ impl<'a, 'b> Iterator for Vp9ParserFrameIterator<'a, 'b> {
             return None;
         }
 
+        if self.frame_index == 0 {
+            if let Err(limit) = self.check_limits() {
+                self.packet = &[];
+                return Some(Err(limit.into()));
+            }
+        }
+
         match self.super_frame.as_ref() {
             Some(super_frame) => {
                 if self.frame_index >= super_frame.frame_count {
@@ -1507,29 +1708,27 @@ impl<'a, 'b> Iterator for Vp9ParserFrameIterator<'a, 'b> {
                 }
 
                 let frame_size = match self.parser.read_frame_size(super_frame.entry_data, super_frame.bytes_size, self.frame_index) {
-                    Err(error) => return Some(Err(error)),
-                    Ok(frame) => frame,
-                };
-
-                let frame = match self.parser.parse_frame(self.packet) {
+                    Err(error) => {
+                        self.packet = &[];
+                        return Some(Err(error));
+                    }
                     Ok(frame) => frame,
-                    error => return Some(error),
                 };
-                self.frame_index += 1;
 
-                let (_, left_over) = self.packet.split_at(frame_size);
+                if frame_size > self.packet.len() {
+                    self.packet = &[];
+                    return Some(Err(Vp9ParserError::InvalidSuperFrameIndex));
+                }
+                let (frame_data, left_over) = self.packet.split_at(frame_size);
                 self.packet = left_over;
+                self.frame_index += 1;
 
-                Some(Ok(frame))
+                // The next frame can still be parsed if this one is broken.
+                Some(self.parser.parse_frame(frame_data))
             }
             None => {
-                let frame = match self.parser.parse_frame(self.packet) {
-                    Ok(frame) => frame,
-                    error => return Some(error),
-                };
-
-                self.packet = &[];
-                Some(Ok(frame))
+                let packet = std::mem::take(&mut self.packet);
+                Some(self.parser.parse_frame(packet))
             }
         }
     }
@@ -1582,4 +1781,72 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn convert_color_description() {
+        let description = ColorDescription::from((ColorSpace::Bt709, ColorRange::StudioSwing));
+        assert_eq!(description.color_primaries, 1);
+        assert_eq!(description.transfer_characteristics, 1);
+        assert_eq!(description.matrix_coefficients, 1);
+        assert!(!description.full_range);
+
+        for color_space in [
+            ColorSpace::Unknown,
+            ColorSpace::Bt601,
+            ColorSpace::Bt709,
+            ColorSpace::Smpte170,
+            ColorSpace::Smpte240,
+            ColorSpace::Bt2020,
+            ColorSpace::Rgb,
+        ] {
+            let description = ColorDescription::from((color_space, ColorRange::FullSwing));
+            let (converted, range) = description.into();
+            assert_eq!(converted, color_space);
+            assert_eq!(range, ColorRange::FullSwing);
+        }
+
+        let reserved = ColorDescription::from((ColorSpace::Reserved, ColorRange::StudioSwing));
+        assert_eq!(reserved.matrix_coefficients, 2);
+
+        for (color_depth, transfer_characteristics) in [
+            (ColorDepth::Depth8, 14),
+            (ColorDepth::Depth10, 14),
+            (ColorDepth::Depth12, 15),
+        ] {
+            let description = ColorDescription::from((
+                ColorSpace::Bt2020,
+                ColorRange::StudioSwing,
+                color_depth,
+            ));
+            assert_eq!(description.color_primaries, 9);
+            assert_eq!(
+                description.transfer_characteristics,
+                transfer_characteristics
+            );
+            assert_eq!(description.matrix_coefficients, 9);
+        }
+        let bt709 = ColorDescription::from((
+            ColorSpace::Bt709,
+            ColorRange::StudioSwing,
+            ColorDepth::Depth12,
+        ));
+        assert_eq!(bt709.transfer_characteristics, 1);
+    }
+
+    #[test]
+    fn reject_invalid_sync_code() {
+        // frame_marker, profile 0, no show_existing_frame, key frame, show_frame, sync code.
+        let header = [0x82, 0x49, 0x83, 0x42];
+        for index in 1..header.len() {
+            let mut data = header.to_vec();
+            data[index] ^= 0x01;
+            data.resize(32, 0);
+
+            let mut parser = Vp9Parser::default();
+            assert!(matches!(
+                parser.parse_packet(&data).next(),
+                Some(Err(Vp9ParserError::InvalidSyncByte))
+            ));
+        }
+    }
 }
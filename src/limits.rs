@@ -0,0 +1,87 @@
+//! Resource limits for untrusted input.
+
+/// Limits that protect against excessive allocations and processing of untrusted input.
+///
+/// Used by `Vp9Parser` and the IVF readers. The defaults only enforce the limits of the
+/// VP9 specification and a maximum packet size of 64 MiB.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct Limits {
+    /// The maximum size of a packet in bytes.
+    pub max_packet_size: usize,
+    /// The maximum width of a frame.
+    pub max_width: u16,
+    /// The maximum height of a frame.
+    pub max_height: u16,
+    /// The maximum area (width * height) of a frame.
+    pub max_area: u64,
+    /// The maximum number of tiles of a frame.
+    pub max_tiles: usize,
+    /// The maximum number of frames inside a super frame.
+    pub max_frames_per_super_frame: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_packet_size: 64 * 1024 * 1024,
+            max_width: u16::MAX,
+            max_height: u16::MAX,
+            max_area: u64::from(u16::MAX) * u64::from(u16::MAX),
+            max_tiles: 256,
+            max_frames_per_super_frame: 8,
+        }
+    }
+}
+
+/// A limit that was exceeded, with the value that exceeded it.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum Limit {
+    /// The size of a packet.
+    PacketSize(usize),
+    /// The width of a frame.
+    Width(u16),
+    /// The height of a frame.
+    Height(u16),
+    /// The area of a frame.
+    Area(u64),
+    /// The number of tiles of a frame.
+    Tiles(usize),
+    /// The number of frames inside a super frame.
+    FramesPerSuperFrame(usize),
+}
+
+impl std::fmt::Display for Limit {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Limit::PacketSize(size) => write!(f, "packet size {}", size),
+            Limit::Width(width) => write!(f, "width {}", width),
+            Limit::Height(height) => write!(f, "height {}", height),
+            Limit::Area(area) => write!(f, "area {}", area),
+            Limit::Tiles(tiles) => write!(f, "{} tiles", tiles),
+            Limit::FramesPerSuperFrame(count) => write!(f, "{} frames per super frame", count),
+        }
+    }
+}
+
+impl Limits {
+    pub(crate) fn check_packet_size(&self, size: usize) -> Result<(), Limit> {
+        if size > self.max_packet_size {
+            return Err(Limit::PacketSize(size));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn check_frame_size(&self, width: u16, height: u16) -> Result<(), Limit> {
+        if width > self.max_width {
+            return Err(Limit::Width(width));
+        }
+        if height > self.max_height {
+            return Err(Limit::Height(height));
+        }
+        let area = u64::from(width) * u64::from(height);
+        if area > self.max_area {
+            return Err(Limit::Area(area));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,45 @@
+//! Picture errors.
+
+/// Errors that can occur when converting pictures.
+#[derive(Debug)]
+pub enum PictureError {
+    /// Invalid picture dimensions.
+    InvalidDimensions,
+    /// The plane with the given index is too small for the picture dimensions.
+    InvalidPlane(usize),
+    /// Unsupported color depth.
+    UnsupportedColorDepth,
+    /// The requested stride is smaller than a row of the picture.
+    InvalidStride(usize),
+    /// The alignment is not a power of two.
+    InvalidAlignment(usize),
+    /// The output buffer is too small. Contains the required size.
+    BufferTooSmall(usize),
+}
+
+impl std::fmt::Display for PictureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            PictureError::InvalidDimensions => {
+                write!(f, "invalid dimensions")
+            }
+            PictureError::InvalidPlane(index) => {
+                write!(f, "plane {} is too small", index)
+            }
+            PictureError::UnsupportedColorDepth => {
+                write!(f, "unsupported color depth")
+            }
+            PictureError::InvalidStride(stride) => {
+                write!(f, "invalid stride: {}", stride)
+            }
+            PictureError::InvalidAlignment(alignment) => {
+                write!(f, "invalid alignment: {}", alignment)
+            }
+            PictureError::BufferTooSmall(size) => {
+                write!(f, "buffer too small, {} bytes are required", size)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PictureError {}
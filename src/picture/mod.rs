@@ -0,0 +1,681 @@
+//! Conversion of decoded pictures into common memory layouts.
+//!
+//! This crate does not contain a decoder. A [`Picture`] describes the planes a decoder produced,
+//! which can then be copied into the layouts GPU uploads and encoders expect or converted into RGB.
+
+pub use error::PictureError;
+pub use rgb::{RgbFormat, RgbPicture};
+
+use crate::{ColorDepth, Subsampling};
+
+mod error;
+mod rgb;
+
+type Result<T> = std::result::Result<T, PictureError>;
+
+/// The memory layouts a picture can be converted into.
+#[derive(Clone, Copy, Debug, Ord, PartialOrd, Eq, PartialEq)]
+pub enum PixelFormat {
+    /// 8 bit, 4:2:0, three planes (Y, U, V).
+    I420,
+    /// 8 bit, 4:2:0, two planes (Y, interleaved UV).
+    Nv12,
+    /// 8 bit, 4:4:4, three planes (Y, U, V).
+    I444,
+    /// 10 bit in the high bits of 16 bit LE words, 4:2:0, two planes (Y, interleaved UV).
+    P010,
+    /// 16 bit LE words, 4:2:0, two planes (Y, interleaved UV).
+    P016,
+}
+
+impl PixelFormat {
+    /// Returns the format that can hold a picture with the given subsampling and color depth
+    /// without losing information. Returns `None` if no such format exists.
+    pub fn preferred(subsampling: Subsampling, color_depth: ColorDepth) -> Option<PixelFormat> {
+        match (color_depth, subsampling) {
+            (ColorDepth::Depth8, Subsampling::Yuv420) => Some(PixelFormat::I420),
+            (ColorDepth::Depth8, _) => Some(PixelFormat::I444),
+            (ColorDepth::Depth10, Subsampling::Yuv420) => Some(PixelFormat::P010),
+            (ColorDepth::Depth12, Subsampling::Yuv420) => Some(PixelFormat::P016),
+            _ => None,
+        }
+    }
+
+    /// The number of planes of the format.
+    pub fn plane_count(&self) -> usize {
+        match self {
+            PixelFormat::I420 | PixelFormat::I444 => 3,
+            PixelFormat::Nv12 | PixelFormat::P010 | PixelFormat::P016 => 2,
+        }
+    }
+
+    /// The chroma subsampling of the format.
+    pub fn subsampling(&self) -> Subsampling {
+        match self {
+            PixelFormat::I444 => Subsampling::Yuv444,
+            _ => Subsampling::Yuv420,
+        }
+    }
+
+    /// The number of bytes used to store a sample.
+    pub fn bytes_per_sample(&self) -> usize {
+        match self {
+            PixelFormat::I420 | PixelFormat::Nv12 | PixelFormat::I444 => 1,
+            PixelFormat::P010 | PixelFormat::P016 => 2,
+        }
+    }
+
+    fn is_semi_planar(&self) -> bool {
+        self.plane_count() == 2
+    }
+
+    // Number of significant bits of a sample.
+    fn sample_bits(&self) -> u32 {
+        match self {
+            PixelFormat::I420 | PixelFormat::Nv12 | PixelFormat::I444 => 8,
+            PixelFormat::P010 => 10,
+            PixelFormat::P016 => 16,
+        }
+    }
+
+    // Number of bits the significant bits are shifted to the left inside the stored word.
+    fn sample_shift(&self) -> u32 {
+        match self {
+            PixelFormat::P010 => 6,
+            _ => 0,
+        }
+    }
+}
+
+/// Options for the memory layout of a converted picture.
+#[derive(Clone, Copy, Debug)]
+pub struct ConvertOptions {
+    /// The stride of the luma plane in bytes. The smallest possible stride is used if `None`.
+    pub stride: Option<usize>,
+    /// The alignment in bytes of all strides and plane offsets. Must be a power of two.
+    pub alignment: usize,
+}
+
+impl Default for ConvertOptions {
+    fn default() -> Self {
+        Self {
+            stride: None,
+            alignment: 1,
+        }
+    }
+}
+
+/// Position of a plane inside a converted picture.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct PlaneLayout {
+    offset: usize,
+    stride: usize,
+    height: usize,
+}
+
+impl PlaneLayout {
+    /// Offset of the first row in bytes.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Distance between two rows in bytes.
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    /// Number of rows.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+}
+
+/// The memory layout of a converted picture.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Layout {
+    format: PixelFormat,
+    width: usize,
+    height: usize,
+    planes: Vec<PlaneLayout>,
+    size: usize,
+}
+
+impl Layout {
+    /// Calculates the layout of a picture with the given format and dimensions.
+    pub fn new(
+        format: PixelFormat,
+        width: usize,
+        height: usize,
+        options: &ConvertOptions,
+    ) -> Result<Self> {
+        if width == 0 || height == 0 {
+            return Err(PictureError::InvalidDimensions);
+        }
+
+        let alignment = options.alignment;
+        if !alignment.is_power_of_two() {
+            return Err(PictureError::InvalidAlignment(alignment));
+        }
+
+        let bytes = format.bytes_per_sample();
+        let (sx, sy) = subsampling_shifts(format.subsampling());
+        let chroma_width = subsampled(width, sx);
+        let chroma_height = subsampled(height, sy);
+
+        let luma_row = width
+            .checked_mul(bytes)
+            .ok_or(PictureError::InvalidDimensions)?;
+        let luma_stride = match options.stride {
+            Some(stride) if stride < luma_row => {
+                return Err(PictureError::InvalidStride(stride));
+            }
+            Some(stride) => stride,
+            None => luma_row,
+        };
+        let luma_stride = align(luma_stride, alignment)?;
+
+        let chroma_stride = if format.is_semi_planar() {
+            let chroma_row = chroma_width
+                .checked_mul(2 * bytes)
+                .ok_or(PictureError::InvalidDimensions)?;
+            align(chroma_row.max(luma_stride), alignment)?
+        } else if sx == 0 {
+            luma_stride
+        } else {
+            align((chroma_width * bytes).max(luma_stride / 2), alignment)?
+        };
+
+        let mut planes = Vec::with_capacity(format.plane_count());
+        let mut offset = 0;
+        for i in 0..format.plane_count() {
+            let (stride, rows) = if i == 0 {
+                (luma_stride, height)
+            } else {
+                (chroma_stride, chroma_height)
+            };
+            offset = align(offset, alignment)?;
+            planes.push(PlaneLayout {
+                offset,
+                stride,
+                height: rows,
+            });
+            offset = stride
+                .checked_mul(rows)
+                .and_then(|size| size.checked_add(offset))
+                .ok_or(PictureError::InvalidDimensions)?;
+        }
+
+        Ok(Self {
+            format,
+            width,
+            height,
+            planes,
+            size: offset,
+        })
+    }
+
+    /// The pixel format.
+    pub fn format(&self) -> PixelFormat {
+        self.format
+    }
+
+    /// The width of the picture.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// The height of the picture.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// The layouts of the planes.
+    pub fn planes(&self) -> &[PlaneLayout] {
+        &self.planes
+    }
+
+    /// The size of the whole picture in bytes.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+}
+
+/// A plane of a decoded picture.
+///
+/// Samples with a color depth above 8 bit are stored as 16 bit LE words.
+#[derive(Clone, Copy, Debug)]
+pub struct Plane<'a> {
+    data: &'a [u8],
+    stride: usize,
+}
+
+impl<'a> Plane<'a> {
+    /// Creates a plane from its data and the distance between two rows in bytes.
+    pub fn new(data: &'a [u8], stride: usize) -> Self {
+        Self { data, stride }
+    }
+
+    /// The data of the plane.
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Distance between two rows in bytes.
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    #[inline]
+    fn sample(&self, x: usize, y: usize, bytes: usize) -> u16 {
+        let index = y * self.stride + x * bytes;
+        if bytes == 1 {
+            u16::from(self.data[index])
+        } else {
+            u16::from_le_bytes([self.data[index], self.data[index + 1]])
+        }
+    }
+}
+
+/// A decoded picture with its Y, U and V planes.
+#[derive(Clone, Debug)]
+pub struct Picture<'a> {
+    width: usize,
+    height: usize,
+    subsampling: Subsampling,
+    color_depth: ColorDepth,
+    planes: [Plane<'a>; 3],
+}
+
+impl<'a> Picture<'a> {
+    /// Creates a picture from the planes of a decoder.
+    pub fn new(
+        width: usize,
+        height: usize,
+        subsampling: Subsampling,
+        color_depth: ColorDepth,
+        planes: [Plane<'a>; 3],
+    ) -> Result<Self> {
+        if width == 0 || height == 0 {
+            return Err(PictureError::InvalidDimensions);
+        }
+
+        let bytes = depth_bytes(color_depth)?;
+        let (sx, sy) = subsampling_shifts(subsampling);
+        for (i, plane) in planes.iter().enumerate() {
+            let (plane_width, plane_height) = if i == 0 {
+                (width, height)
+            } else {
+                (subsampled(width, sx), subsampled(height, sy))
+            };
+
+            let row = plane_width
+                .checked_mul(bytes)
+                .ok_or(PictureError::InvalidDimensions)?;
+            let required = plane
+                .stride
+                .checked_mul(plane_height - 1)
+                .and_then(|s| s.checked_add(row));
+            let fits = matches!(required, Some(size) if plane.data.len() >= size);
+            if plane.stride < row || !fits {
+                return Err(PictureError::InvalidPlane(i));
+            }
+        }
+
+        Ok(Self {
+            width,
+            height,
+            subsampling,
+            color_depth,
+            planes,
+        })
+    }
+
+    /// The width of the picture.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// The height of the picture.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// The chroma subsampling of the picture.
+    pub fn subsampling(&self) -> Subsampling {
+        self.subsampling
+    }
+
+    /// The color depth of the picture.
+    pub fn color_depth(&self) -> ColorDepth {
+        self.color_depth
+    }
+
+    /// The Y, U and V planes.
+    pub fn planes(&self) -> &[Plane<'a>; 3] {
+        &self.planes
+    }
+
+    /// Converts the picture into a newly allocated buffer with the given format.
+    ///
+    /// Chroma is resampled if the subsampling of the format differs from the picture and samples
+    /// are rescaled if the color depth differs.
+    pub fn convert(
+        &self,
+        format: PixelFormat,
+        options: &ConvertOptions,
+    ) -> Result<ConvertedPicture> {
+        let layout = Layout::new(format, self.width, self.height, options)?;
+        let mut data = vec![0u8; layout.size()];
+        self.convert_into(&layout, &mut data)?;
+        Ok(ConvertedPicture { layout, data })
+    }
+
+    /// Converts the picture into the given buffer using the given layout.
+    ///
+    /// The padding between rows and planes is not touched.
+    pub fn convert_into(&self, layout: &Layout, buffer: &mut [u8]) -> Result<()> {
+        if layout.width != self.width || layout.height != self.height {
+            return Err(PictureError::InvalidDimensions);
+        }
+        if buffer.len() < layout.size {
+            return Err(PictureError::BufferTooSmall(layout.size));
+        }
+
+        let format = layout.format;
+        let source_bits = depth_bits(self.color_depth)?;
+        let source_bytes = depth_bytes(self.color_depth)?;
+        let target_bytes = format.bytes_per_sample();
+        let target_bits = format.sample_bits();
+        let shift = format.sample_shift();
+
+        let luma = &layout.planes[0];
+        for y in 0..self.height {
+            let row = luma.offset + y * luma.stride;
+            for x in 0..self.width {
+                let value = self.planes[0].sample(x, y, source_bytes);
+                let value = rescale(value, source_bits, target_bits) << shift;
+                write_sample(buffer, row + x * target_bytes, value, target_bytes);
+            }
+        }
+
+        let (source_sx, source_sy) = subsampling_shifts(self.subsampling);
+        let (target_sx, target_sy) = subsampling_shifts(format.subsampling());
+        let chroma_width = subsampled(self.width, target_sx);
+        let chroma_height = subsampled(self.height, target_sy);
+
+        for component in 1..3 {
+            let (plane, interleave, position) = if format.is_semi_planar() {
+                (&layout.planes[1], 2, component - 1)
+            } else {
+                (&layout.planes[component], 1, 0)
+            };
+
+            for cy in 0..chroma_height {
+                let y0 = (cy << target_sy) >> source_sy;
+                let y1 = ((((cy + 1) << target_sy).min(self.height)) - 1) >> source_sy;
+                let row = plane.offset + cy * plane.stride;
+
+                for cx in 0..chroma_width {
+                    let x0 = (cx << target_sx) >> source_sx;
+                    let x1 = ((((cx + 1) << target_sx).min(self.width)) - 1) >> source_sx;
+
+                    let mut sum = 0u32;
+                    let mut count = 0u32;
+                    for sy in y0..=y1 {
+                        for sx in x0..=x1 {
+                            sum += u32::from(self.planes[component].sample(sx, sy, source_bytes));
+                            count += 1;
+                        }
+                    }
+                    let average = u16::try_from((sum + count / 2) / count).unwrap_or(u16::MAX);
+
+                    let value = rescale(average, source_bits, target_bits) << shift;
+                    let index = row + (cx * interleave + position) * target_bytes;
+                    write_sample(buffer, index, value, target_bytes);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A picture that was converted into a pixel format.
+#[derive(Clone, Debug)]
+pub struct ConvertedPicture {
+    layout: Layout,
+    data: Vec<u8>,
+}
+
+impl ConvertedPicture {
+    /// The memory layout of the picture.
+    pub fn layout(&self) -> &Layout {
+        &self.layout
+    }
+
+    /// The data of the picture.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Destroys the picture and returns the underlying data buffer.
+    pub fn into_vec(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+/// Returns the horizontal and vertical subsampling as shifts.
+fn subsampling_shifts(subsampling: Subsampling) -> (usize, usize) {
+    match subsampling {
+        Subsampling::Yuv444 => (0, 0),
+        Subsampling::Yuv440 => (0, 1),
+        Subsampling::Yuv422 => (1, 0),
+        Subsampling::Yuv420 => (1, 1),
+    }
+}
+
+/// Returns the size of a plane dimension after subsampling, rounding up.
+fn subsampled(size: usize, shift: usize) -> usize {
+    size.div_ceil(1 << shift)
+}
+
+fn depth_bits(color_depth: ColorDepth) -> Result<u32> {
+    match color_depth {
+        ColorDepth::Depth8 => Ok(8),
+        ColorDepth::Depth10 => Ok(10),
+        ColorDepth::Depth12 => Ok(12),
+        ColorDepth::Unknown => Err(PictureError::UnsupportedColorDepth),
+    }
+}
+
+fn depth_bytes(color_depth: ColorDepth) -> Result<usize> {
+    Ok(if depth_bits(color_depth)? > 8 { 2 } else { 1 })
+}
+
+fn align(value: usize, alignment: usize) -> Result<usize> {
+    value
+        .checked_add(alignment - 1)
+        .map(|value| value & !(alignment - 1))
+        .ok_or(PictureError::InvalidDimensions)
+}
+
+/// Rescales a sample from one bit depth to another, rounding to the nearest value.
+#[inline]
+fn rescale(value: u16, from: u32, to: u32) -> u16 {
+    if to > from {
+        value << (to - from)
+    } else if to < from {
+        let shift = from - to;
+        let max = u16::MAX >> (16 - to);
+        ((u32::from(value) + (1 << (shift - 1))) >> shift)
+            .try_into()
+            .map_or(max, |value: u16| value.min(max))
+    } else {
+        value
+    }
+}
+
+#[inline]
+fn write_sample(buffer: &mut [u8], index: usize, value: u16, bytes: usize) {
+    let [low, high] = value.to_le_bytes();
+    buffer[index] = low;
+    if bytes == 2 {
+        buffer[index + 1] = high;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+
+    #[test]
+    fn convert_to_nv12() {
+        let y: Vec<u8> = (0..16).collect();
+        let u = vec![100, 101, 102, 103];
+        let v = vec![200, 201, 202, 203];
+        let picture = Picture::new(
+            4,
+            4,
+            Subsampling::Yuv420,
+            ColorDepth::Depth8,
+            [Plane::new(&y, 4), Plane::new(&u, 2), Plane::new(&v, 2)],
+        )
+        .unwrap();
+
+        let options = ConvertOptions {
+            stride: None,
+            alignment: 8,
+        };
+        let converted = picture.convert(PixelFormat::Nv12, &options).unwrap();
+        let layout = converted.layout();
+
+        assert_eq!(layout.planes()[0].stride(), 8);
+        assert_eq!(layout.planes()[1].offset(), 32);
+        assert_eq!(layout.size(), 48);
+        assert_eq!(&converted.data()[8..12], &[4, 5, 6, 7]);
+        assert_eq!(&converted.data()[32..36], &[100, 200, 101, 201]);
+        assert_eq!(&converted.data()[40..44], &[102, 202, 103, 203]);
+    }
+
+    #[test]
+    fn convert_to_p010() {
+        let y: Vec<u8> = [1023u16, 512, 0, 1]
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+        let uv: Vec<u8> = [16u16, 32, 48, 64]
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+        let picture = Picture::new(
+            2,
+            2,
+            Subsampling::Yuv444,
+            ColorDepth::Depth10,
+            [Plane::new(&y, 4), Plane::new(&uv, 4), Plane::new(&uv, 4)],
+        )
+        .unwrap();
+
+        let converted = picture
+            .convert(PixelFormat::P010, &ConvertOptions::default())
+            .unwrap();
+        let data = converted.data();
+
+        assert_eq!(u16::from_le_bytes([data[0], data[1]]), 1023 << 6);
+        assert_eq!(u16::from_le_bytes([data[6], data[7]]), 1 << 6);
+        // The chroma of 4:4:4 is averaged down to 4:2:0.
+        assert_eq!(u16::from_le_bytes([data[8], data[9]]), 40 << 6);
+    }
+
+    #[test]
+    fn convert_to_i420() {
+        // The source rows are padded to a stride of 6.
+        let y = [1u8, 2, 3, 4, 0, 0, 5, 6, 7, 8, 0, 0];
+        let u = [100u8, 101];
+        let v = [200u8, 201];
+        let picture = Picture::new(
+            4,
+            2,
+            Subsampling::Yuv420,
+            ColorDepth::Depth8,
+            [Plane::new(&y, 6), Plane::new(&u, 2), Plane::new(&v, 2)],
+        )
+        .unwrap();
+
+        let converted = picture
+            .convert(PixelFormat::I420, &ConvertOptions::default())
+            .unwrap();
+        let layout = converted.layout();
+
+        assert_eq!(layout.planes().len(), 3);
+        assert_eq!(layout.planes()[0].stride(), 4);
+        assert_eq!(layout.planes()[1].offset(), 8);
+        assert_eq!(layout.planes()[2].offset(), 10);
+        assert_eq!(
+            converted.data(),
+            &[1, 2, 3, 4, 5, 6, 7, 8, 100, 101, 200, 201]
+        );
+    }
+
+    #[test]
+    fn convert_420_to_i444() {
+        // 3x3 with 2x2 chroma, the last row and column share the chroma of the one before.
+        let y = [0u8; 9];
+        let u = [10u8, 20, 30, 40];
+        let v = [50u8, 60, 70, 80];
+        let picture = Picture::new(
+            3,
+            3,
+            Subsampling::Yuv420,
+            ColorDepth::Depth8,
+            [Plane::new(&y, 3), Plane::new(&u, 2), Plane::new(&v, 2)],
+        )
+        .unwrap();
+
+        let converted = picture
+            .convert(PixelFormat::I444, &ConvertOptions::default())
+            .unwrap();
+        let layout = converted.layout();
+        let data = converted.data();
+
+        assert_eq!(layout.planes()[1].stride(), 3);
+        let u = &data[layout.planes()[1].offset()..layout.planes()[2].offset()];
+        let v = &data[layout.planes()[2].offset()..];
+        assert_eq!(u, &[10, 10, 20, 10, 10, 20, 30, 30, 40]);
+        assert_eq!(v, &[50, 50, 60, 50, 50, 60, 70, 70, 80]);
+    }
+
+    #[test]
+    fn convert_to_p016() {
+        let y: Vec<u8> = [4095u16, 0, 2048, 1]
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+        let u = 256u16.to_le_bytes();
+        let v = 3840u16.to_le_bytes();
+        let picture = Picture::new(
+            2,
+            2,
+            Subsampling::Yuv420,
+            ColorDepth::Depth12,
+            [Plane::new(&y, 4), Plane::new(&u, 2), Plane::new(&v, 2)],
+        )
+        .unwrap();
+
+        let converted = picture
+            .convert(PixelFormat::P016, &ConvertOptions::default())
+            .unwrap();
+        let layout = converted.layout();
+
+        assert_eq!(layout.planes().len(), 2);
+        assert_eq!(layout.planes()[1].offset(), 8);
+        // 12 bit samples fill the whole 16 bit word.
+        let expected: Vec<u8> = [4095u16 << 4, 0, 2048 << 4, 1 << 4, 256 << 4, 3840 << 4]
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+        assert_eq!(converted.data(), expected.as_slice());
+    }
+}
@@ -0,0 +1,381 @@
+//! Conversion of decoded pictures into RGB.
+
+use super::{
+    align, depth_bits, depth_bytes, subsampling_shifts, ConvertOptions, Picture, PictureError,
+    Result,
+};
+use crate::{ColorRange, ColorSpace};
+
+/// Fixed point precision of the matrix coefficients.
+const COEFFICIENT_BITS: u32 = 16;
+
+// Coefficients of the YCbCr to RGB matrices: [Cr to R, Cb to G, Cr to G, Cb to B].
+const BT601: [i64; 4] = [91881, -22553, -46802, 116130];
+const BT709: [i64; 4] = [103206, -12276, -30679, 121609];
+const SMPTE240: [i64; 4] = [103285, -14852, -31236, 119669];
+const BT2020_NCL: [i64; 4] = [96639, -10784, -37444, 123299];
+
+/// The RGB formats a picture can be converted into.
+#[derive(Clone, Copy, Debug, Ord, PartialOrd, Eq, PartialEq)]
+pub enum RgbFormat {
+    /// 8 bit R, G, B.
+    Rgb8,
+    /// 8 bit R, G, B, A. Alpha is always opaque.
+    Rgba8,
+    /// 16 bit LE R, G, B.
+    Rgb16,
+    /// 16 bit LE R, G, B, A. Alpha is always opaque.
+    Rgba16,
+}
+
+impl RgbFormat {
+    /// The number of channels of a pixel.
+    pub fn channels(&self) -> usize {
+        match self {
+            RgbFormat::Rgb8 | RgbFormat::Rgb16 => 3,
+            RgbFormat::Rgba8 | RgbFormat::Rgba16 => 4,
+        }
+    }
+
+    /// The number of bytes used to store a channel.
+    pub fn bytes_per_sample(&self) -> usize {
+        match self {
+            RgbFormat::Rgb8 | RgbFormat::Rgba8 => 1,
+            RgbFormat::Rgb16 | RgbFormat::Rgba16 => 2,
+        }
+    }
+
+    /// The number of bytes used to store a pixel.
+    pub fn bytes_per_pixel(&self) -> usize {
+        self.channels() * self.bytes_per_sample()
+    }
+}
+
+/// A picture that was converted into RGB.
+#[derive(Clone, Debug)]
+pub struct RgbPicture {
+    format: RgbFormat,
+    width: usize,
+    height: usize,
+    stride: usize,
+    data: Vec<u8>,
+}
+
+impl RgbPicture {
+    /// The RGB format of the picture.
+    pub fn format(&self) -> RgbFormat {
+        self.format
+    }
+
+    /// The width of the picture.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// The height of the picture.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Distance between two rows in bytes.
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    /// The data of the picture.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Destroys the picture and returns the underlying data buffer.
+    pub fn into_vec(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+impl<'a> Picture<'a> {
+    /// Converts the picture into RGB.
+    ///
+    /// The matrix is chosen by the color space and the samples are scaled by the color range,
+    /// which should both be taken from the frame the picture was decoded from. An unknown or
+    /// reserved color space uses BT.601. Pictures with the `Rgb` color space store
+    /// the G, B and R channels in the Y, U and V planes. Chroma is upsampled by repeating
+    /// the nearest sample.
+    pub fn convert_to_rgb(
+        &self,
+        format: RgbFormat,
+        color_space: ColorSpace,
+        color_range: ColorRange,
+        options: &ConvertOptions,
+    ) -> Result<RgbPicture> {
+        let alignment = options.alignment;
+        if !alignment.is_power_of_two() {
+            return Err(PictureError::InvalidAlignment(alignment));
+        }
+
+        let row = self
+            .width
+            .checked_mul(format.bytes_per_pixel())
+            .ok_or(PictureError::InvalidDimensions)?;
+        let stride = match options.stride {
+            Some(stride) if stride < row => return Err(PictureError::InvalidStride(stride)),
+            Some(stride) => stride,
+            None => row,
+        };
+        let stride = align(stride, alignment)?;
+        let size = stride
+            .checked_mul(self.height)
+            .ok_or(PictureError::InvalidDimensions)?;
+
+        let bits = depth_bits(self.color_depth)?;
+        let bytes = depth_bytes(self.color_depth)?;
+        let (sx, sy) = subsampling_shifts(self.subsampling);
+        let range = SampleRange::new(bits, color_range);
+        let matrix = matrix(color_space);
+
+        let out_bytes = format.bytes_per_sample();
+        let out_max: i64 = if out_bytes == 1 { 0xFF } else { 0xFFFF };
+
+        let mut data = vec![0u8; size];
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let luma = i64::from(self.planes[0].sample(x, y, bytes));
+                let cb = i64::from(self.planes[1].sample(x >> sx, y >> sy, bytes));
+                let cr = i64::from(self.planes[2].sample(x >> sx, y >> sy, bytes));
+
+                let rgb = match matrix {
+                    Some(matrix) => range.ycbcr_to_rgb(luma, cb, cr, &matrix, out_max),
+                    None => [
+                        range.scale_full(cr, out_max),
+                        range.scale_full(luma, out_max),
+                        range.scale_full(cb, out_max),
+                    ],
+                };
+
+                let index = y * stride + x * format.bytes_per_pixel();
+                let pixel = &mut data[index..index + format.bytes_per_pixel()];
+                let channels = rgb.iter().chain(std::iter::once(&out_max));
+                for (chunk, value) in pixel.chunks_exact_mut(out_bytes).zip(channels) {
+                    let value = u16::try_from(*value).unwrap_or(u16::MAX).to_le_bytes();
+                    chunk.copy_from_slice(&value[..out_bytes]);
+                }
+            }
+        }
+
+        Ok(RgbPicture {
+            format,
+            width: self.width,
+            height: self.height,
+            stride,
+            data,
+        })
+    }
+}
+
+/// Returns the matrix for the color space or `None` for the identity matrix.
+fn matrix(color_space: ColorSpace) -> Option<[i64; 4]> {
+    match color_space {
+        ColorSpace::Unknown | ColorSpace::Reserved | ColorSpace::Bt601 | ColorSpace::Smpte170 => {
+            Some(BT601)
+        }
+        ColorSpace::Bt709 => Some(BT709),
+        ColorSpace::Smpte240 => Some(SMPTE240),
+        ColorSpace::Bt2020 => Some(BT2020_NCL),
+        ColorSpace::Rgb => None,
+    }
+}
+
+/// Offsets and ranges of the luma and chroma samples.
+struct SampleRange {
+    luma_offset: i64,
+    luma_range: i64,
+    chroma_offset: i64,
+    chroma_range: i64,
+}
+
+impl SampleRange {
+    fn new(bits: u32, color_range: ColorRange) -> Self {
+        match color_range {
+            ColorRange::StudioSwing => Self {
+                luma_offset: 16 << (bits - 8),
+                luma_range: 219 << (bits - 8),
+                chroma_offset: 128 << (bits - 8),
+                chroma_range: 224 << (bits - 8),
+            },
+            ColorRange::FullSwing => Self {
+                luma_offset: 0,
+                luma_range: (1 << bits) - 1,
+                chroma_offset: 1 << (bits - 1),
+                chroma_range: (1 << bits) - 1,
+            },
+        }
+    }
+
+    /// Scales a sample that uses the luma range to the full output range.
+    fn scale_full(&self, value: i64, out_max: i64) -> i64 {
+        divide_clamped(
+            (value - self.luma_offset) * out_max,
+            self.luma_range,
+            out_max,
+        )
+    }
+
+    fn ycbcr_to_rgb(
+        &self,
+        luma: i64,
+        cb: i64,
+        cr: i64,
+        matrix: &[i64; 4],
+        out_max: i64,
+    ) -> [i64; 3] {
+        let y = ((luma - self.luma_offset) * self.chroma_range) << COEFFICIENT_BITS;
+        let cb = (cb - self.chroma_offset) * self.luma_range;
+        let cr = (cr - self.chroma_offset) * self.luma_range;
+        let divisor = (self.luma_range * self.chroma_range) << COEFFICIENT_BITS;
+
+        let r = y + matrix[0] * cr;
+        let g = y + matrix[1] * cb + matrix[2] * cr;
+        let b = y + matrix[3] * cb;
+
+        [r, g, b].map(|value| divide_clamped(value * out_max, divisor, out_max))
+    }
+}
+
+/// Divides and rounds to the nearest value inside `0..=max`.
+fn divide_clamped(value: i64, divisor: i64, max: i64) -> i64 {
+    if value <= 0 {
+        0
+    } else {
+        ((value + divisor / 2) / divisor).min(max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+    use crate::{picture::Plane, ColorDepth, Subsampling};
+
+    #[test]
+    fn convert_bt601_studio_swing() {
+        // White, black and red.
+        let y = [235u8, 16, 81];
+        let u = [128u8, 128, 90];
+        let v = [128u8, 128, 240];
+        let picture = Picture::new(
+            3,
+            1,
+            Subsampling::Yuv444,
+            ColorDepth::Depth8,
+            [Plane::new(&y, 3), Plane::new(&u, 3), Plane::new(&v, 3)],
+        )
+        .unwrap();
+
+        let rgb = picture
+            .convert_to_rgb(
+                RgbFormat::Rgba8,
+                ColorSpace::Bt601,
+                ColorRange::StudioSwing,
+                &ConvertOptions::default(),
+            )
+            .unwrap();
+        let data = rgb.data();
+
+        assert_eq!(&data[0..4], &[255, 255, 255, 255]);
+        assert_eq!(&data[4..8], &[0, 0, 0, 255]);
+        assert!(data[8] >= 254 && data[9] <= 1 && data[10] <= 1);
+    }
+
+    #[test]
+    fn convert_identity_matrix() {
+        let g = [10u8];
+        let b = [20u8];
+        let r = [30u8];
+        let picture = Picture::new(
+            1,
+            1,
+            Subsampling::Yuv444,
+            ColorDepth::Depth8,
+            [Plane::new(&g, 1), Plane::new(&b, 1), Plane::new(&r, 1)],
+        )
+        .unwrap();
+
+        let rgb = picture
+            .convert_to_rgb(
+                RgbFormat::Rgb8,
+                ColorSpace::Rgb,
+                ColorRange::FullSwing,
+                &ConvertOptions::default(),
+            )
+            .unwrap();
+
+        assert_eq!(rgb.data(), &[30, 10, 20]);
+    }
+
+    #[test]
+    fn convert_420_to_rgb() {
+        // 3x1 with 2x1 chroma: white, white and red. The third pixel uses the second
+        // chroma sample.
+        let y = [235u8, 235, 81];
+        let u = [128u8, 90];
+        let v = [128u8, 240];
+        let picture = Picture::new(
+            3,
+            1,
+            Subsampling::Yuv420,
+            ColorDepth::Depth8,
+            [Plane::new(&y, 3), Plane::new(&u, 2), Plane::new(&v, 2)],
+        )
+        .unwrap();
+
+        let rgb = picture
+            .convert_to_rgb(
+                RgbFormat::Rgb8,
+                ColorSpace::Bt601,
+                ColorRange::StudioSwing,
+                &ConvertOptions::default(),
+            )
+            .unwrap();
+        let data = rgb.data();
+
+        assert_eq!(&data[0..6], &[255, 255, 255, 255, 255, 255]);
+        assert!(data[6] >= 254 && data[7] <= 1 && data[8] <= 1);
+    }
+
+    #[test]
+    fn convert_to_rgba16() {
+        // White and black at 10 bit.
+        let y: Vec<u8> = [940u16, 64].iter().flat_map(|v| v.to_le_bytes()).collect();
+        let uv: Vec<u8> = [512u16, 512].iter().flat_map(|v| v.to_le_bytes()).collect();
+        let picture = Picture::new(
+            2,
+            1,
+            Subsampling::Yuv444,
+            ColorDepth::Depth10,
+            [Plane::new(&y, 4), Plane::new(&uv, 4), Plane::new(&uv, 4)],
+        )
+        .unwrap();
+
+        let options = ConvertOptions {
+            stride: None,
+            alignment: 32,
+        };
+        let rgb = picture
+            .convert_to_rgb(
+                RgbFormat::Rgba16,
+                ColorSpace::Bt709,
+                ColorRange::StudioSwing,
+                &options,
+            )
+            .unwrap();
+
+        assert_eq!(rgb.stride(), 32);
+        assert_eq!(rgb.data().len(), 32);
+        let expected: Vec<u8> = [0xFFFFu16, 0xFFFF, 0xFFFF, 0xFFFF, 0, 0, 0, 0xFFFF]
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+        assert_eq!(&rgb.data()[..16], expected.as_slice());
+    }
+}
@@ -0,0 +1,97 @@
+//! Rewriting of uncompressed headers inside frames and packets.
+
+use crate::{Frame, Result, UncompressedHeader, Vp9Parser, Vp9ParserError};
+
+/// Maximum number of frames inside a super frame.
+pub(crate) const MAX_FRAMES_IN_SUPER_FRAME: usize = 8;
+
+/// Rewrites the uncompressed header of a frame and returns the new frame data.
+///
+/// The closure can change any field of the header. The new header is written in front of
+/// the untouched compressed header and tile data, so only changes that don't alter how the
+/// rest of the frame is decoded are safe, like the color config, the render size,
+/// `refresh_frame_context` or the loop filter level.
+///
+/// ```no_run
+/// # use vp9_parser::{rewrite_frame, ColorSpace, Vp9Parser};
+/// # let packet = vec![];
+/// let mut parser = Vp9Parser::new();
+/// for frame in parser.parse_packet(&packet) {
+///     let data = rewrite_frame(&frame?, |header| header.color_space = ColorSpace::Bt709)?;
+/// }
+/// # Ok::<(), vp9_parser::Vp9ParserError>(())
+/// ```
+pub fn rewrite_frame<F>(frame: &Frame, f: F) -> Result<Vec<u8>>
+where
+    F: FnOnce(&mut UncompressedHeader),
+{
+    let mut header = UncompressedHeader::from(frame);
+    f(&mut header);
+
+    let mut data = header.to_bytes()?;
+    data.extend_from_slice(frame.compressed_header_and_tile_data());
+    Ok(data)
+}
+
+/// Rewrites the uncompressed headers of all frames inside a packet and returns the new packet.
+///
+/// The closure is called with the index of the frame inside the packet and its header.
+/// The super frame index is rebuilt if the packet is a super frame, so the frame sizes
+/// may change. See [`rewrite_frame`] for which changes are safe.
+///
+/// The parser must be fed the packets in bitstream order, like [`Vp9Parser::parse_packet`].
+pub fn rewrite_packet<F>(parser: &mut Vp9Parser, packet: &[u8], mut f: F) -> Result<Vec<u8>>
+where
+    F: FnMut(usize, &mut UncompressedHeader),
+{
+    let mut frames = parser.parse_packet(packet);
+    let is_super_frame = frames.is_super_frame();
+
+    let mut rewritten = Vec::new();
+    for (index, frame) in frames.by_ref().enumerate() {
+        rewritten.push(rewrite_frame(&frame?, |header| f(index, header))?);
+    }
+
+    if is_super_frame {
+        write_super_frame(&rewritten)
+    } else {
+        Ok(rewritten.concat())
+    }
+}
+
+/// Concatenates the frames and appends a super frame index.
+pub(crate) fn write_super_frame<T: AsRef<[u8]>>(frames: &[T]) -> Result<Vec<u8>> {
+    if frames.is_empty() || frames.len() > MAX_FRAMES_IN_SUPER_FRAME {
+        return Err(Vp9ParserError::InvalidSuperFrameIndex);
+    }
+
+    let max_size = frames
+        .iter()
+        .map(|frame| frame.as_ref().len())
+        .max()
+        .unwrap_or_default();
+    let max_size = u32::try_from(max_size).map_err(|_| Vp9ParserError::InvalidSuperFrameIndex)?;
+    let bytes_size: u8 = match max_size {
+        0..=0xFF => 1,
+        0x100..=0xFFFF => 2,
+        0x10000..=0xFF_FFFF => 3,
+        _ => 4,
+    };
+
+    let frame_count: u8 = frames.len().try_into()?;
+    let marker = 0b1100_0000 | ((bytes_size - 1) << 3) | (frame_count - 1);
+
+    let mut data = Vec::new();
+    for frame in frames {
+        data.extend_from_slice(frame.as_ref());
+    }
+
+    data.push(marker);
+    for frame in frames {
+        let size = u32::try_from(frame.as_ref().len())?.to_le_bytes();
+        data.extend_from_slice(&size[..usize::from(bytes_size)]);
+    }
+    data.push(marker);
+
+    Ok(data)
+}
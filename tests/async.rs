@@ -0,0 +1,110 @@
+#![cfg(feature = "async")]
+
+use std::{fs, io::Cursor};
+
+use futures::{executor::block_on, io, StreamExt, TryStreamExt};
+use vp9_parser::ivf::{AsyncIvf, Frame, Ivf, IvfError, IvfOptions};
+
+use common::{read_all, FILES};
+
+mod common;
+
+fn read_sync(data: &[u8]) -> Vec<Frame> {
+    read_all(&mut Ivf::new(Cursor::new(data)).unwrap()).unwrap()
+}
+
+#[test]
+pub fn read_async_ivf() {
+    for path in FILES {
+        let data = fs::read(path).unwrap();
+        let expected = read_sync(&data);
+        let ivf = Ivf::new(Cursor::new(&data)).unwrap();
+
+        block_on(async {
+            let mut async_ivf = AsyncIvf::new(io::Cursor::new(&data)).await.unwrap();
+            assert_eq!(async_ivf.four_cc(), ivf.four_cc());
+            assert_eq!(async_ivf.width(), ivf.width());
+            assert_eq!(async_ivf.height(), ivf.height());
+            assert_eq!(async_ivf.frame_count(), ivf.frame_count());
+            assert_eq!(async_ivf.timebase(), ivf.timebase());
+
+            let frames: Vec<Frame> = async_ivf.frames().try_collect().await.unwrap();
+            assert_eq!(frames.len(), expected.len());
+            for (frame, expected) in frames.iter().zip(&expected) {
+                assert_eq!(frame.timestamp, expected.timestamp);
+                assert_eq!(frame.packet, expected.packet);
+            }
+            assert!(async_ivf.read_frame().await.unwrap().is_none());
+        });
+    }
+}
+
+#[test]
+pub fn read_truncated_async_ivf() {
+    let data = fs::read("tests/data/320-24-crf.ivf").unwrap();
+    let expected = read_sync(&data);
+    let last = expected.last().unwrap();
+    let truncated = &data[..data.len() - last.packet.len() / 2];
+
+    block_on(async {
+        // The stream ends after the error.
+        let mut ivf = AsyncIvf::new(io::Cursor::new(truncated)).await.unwrap();
+        let frames: Vec<_> = ivf.frames().collect().await;
+        assert_eq!(frames.len(), expected.len());
+        assert!(matches!(
+            frames.last().unwrap(),
+            Err(IvfError::UnexpectedFileEnding)
+        ));
+
+        let options = IvfOptions {
+            recover: true,
+            ..Default::default()
+        };
+        let mut ivf = AsyncIvf::with_options(io::Cursor::new(truncated), &options)
+            .await
+            .unwrap();
+        let frames: Vec<Frame> = ivf.frames().try_collect().await.unwrap();
+        assert!(ivf.is_truncated());
+        assert_eq!(frames.len(), expected.len());
+        assert!(last.packet.starts_with(&frames.last().unwrap().packet));
+    });
+}
+
+#[test]
+pub fn recover_corrupted_async_ivf() {
+    let mut data = fs::read("tests/data/320-24-crf.ivf").unwrap();
+    let expected = read_sync(&data);
+
+    // Corrupt the size of the sixth frame.
+    let offset = 32
+        + expected[..5]
+            .iter()
+            .map(|frame| 12 + frame.packet.len())
+            .sum::<usize>();
+    data[offset..offset + 4].copy_from_slice(&[0xFF; 4]);
+
+    block_on(async {
+        let options = IvfOptions {
+            recover: true,
+            ..Default::default()
+        };
+        let mut ivf = AsyncIvf::with_options(io::Cursor::new(&data), &options)
+            .await
+            .unwrap();
+        let frames: Vec<Frame> = ivf.frames().try_collect().await.unwrap();
+        assert!(!ivf.is_truncated());
+        assert_eq!(
+            ivf.skipped_bytes(),
+            u64::try_from(12 + expected[5].packet.len()).unwrap()
+        );
+
+        let expected: Vec<u64> = expected
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != 5)
+            .map(|(_, frame)| frame.timestamp)
+            .collect();
+        let timestamps: Vec<u64> = frames.iter().map(|frame| frame.timestamp).collect();
+        assert_eq!(timestamps, expected);
+    });
+}
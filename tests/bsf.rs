@@ -0,0 +1,431 @@
+use vp9_parser::{
+    bsf::{
+        BitstreamFilter, ConstantFrameRate, FrameThinning, Packet, RawReorder, SuperFrameMerge,
+        SuperFrameSplit, ThinningMode,
+    },
+    ivf::Timebase,
+    rewrite_packet, Vp9Parser, Vp9ParserError,
+};
+
+use common::read_packets;
+
+mod common;
+
+#[test]
+pub fn split_super_frames() {
+    let packets = read_packets("tests/data/320-24-cq.ivf");
+    let mut filter = SuperFrameSplit::new();
+    let mut parser = Vp9Parser::default();
+    let mut split_parser = Vp9Parser::default();
+
+    let mut super_frames = 0;
+    for packet in packets {
+        let frames = parser
+            .parse_packet(&packet.data)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        if frames.len() > 1 {
+            super_frames += 1;
+        }
+
+        let split = filter.filter(packet.clone()).unwrap();
+        assert_eq!(split.len(), frames.len());
+
+        for (output, frame) in split.iter().zip(frames.iter()) {
+            assert_eq!(output.timestamp, packet.timestamp);
+            assert_eq!(output.hidden, !frame.show_frame());
+
+            let mut iterator = split_parser.parse_packet(&output.data);
+            assert!(!iterator.is_super_frame());
+            let split_frame = iterator.next().unwrap().unwrap();
+            assert!(iterator.next().is_none());
+            assert_eq!(split_frame.tile_data(), frame.tile_data());
+        }
+    }
+
+    assert_ne!(super_frames, 0);
+    assert!(filter.flush().unwrap().is_empty());
+}
+
+#[test]
+pub fn merge_split_frames() {
+    let packets = read_packets("tests/data/320-24-cq.ivf");
+    let mut split = SuperFrameSplit::new();
+    let mut merge = SuperFrameMerge::new();
+
+    let mut merged = Vec::new();
+    for packet in packets.iter() {
+        for frame in split.filter(packet.clone()).unwrap() {
+            merged.extend(merge.filter(frame).unwrap());
+        }
+    }
+    merged.extend(merge.flush().unwrap());
+
+    assert_eq!(merged.len(), packets.len());
+    for (merged, packet) in merged.iter().zip(packets.iter()) {
+        assert_eq!(merged.timestamp, packet.timestamp);
+        assert_eq!(merged.data, packet.data);
+    }
+}
+
+#[test]
+pub fn merge_rejects_super_frames() {
+    let packets = read_packets("tests/data/320-24-cq.ivf");
+    let super_frame = packets
+        .into_iter()
+        .find(|packet| {
+            Vp9Parser::default()
+                .parse_packet(&packet.data)
+                .is_super_frame()
+        })
+        .unwrap();
+
+    let mut merge = SuperFrameMerge::new();
+    assert!(matches!(
+        merge.filter(super_frame),
+        Err(Vp9ParserError::UnexpectedSuperFrameIndex)
+    ));
+}
+
+#[test]
+pub fn merge_frames_ending_with_a_marker_byte() {
+    let packets = read_packets("tests/data/320-24-crf.ivf");
+
+    // The last byte looks like a super frame marker, but there is no index in front of it.
+    let mut data = packets[0].data.clone();
+    data.push(0b1100_0001);
+    let packet = Packet {
+        timestamp: packets[0].timestamp,
+        data: data.clone(),
+        hidden: false,
+    };
+
+    let mut merge = SuperFrameMerge::new();
+    let merged = merge.filter(packet).unwrap();
+    assert_eq!(merged.len(), 1);
+    assert_eq!(merged[0].data, data);
+}
+
+#[test]
+pub fn merge_long_runs_of_hidden_frames() {
+    let packets = read_packets("tests/data/320-24-cq.ivf");
+    let mut split = SuperFrameSplit::new();
+    let frames: Vec<Packet> = packets
+        .iter()
+        .flat_map(|packet| split.filter(packet.clone()).unwrap())
+        .collect();
+    let hidden = frames.iter().find(|frame| frame.hidden).unwrap();
+    let displayed = frames.iter().find(|frame| !frame.hidden).unwrap();
+
+    let mut merge = SuperFrameMerge::new();
+    let mut merged = Vec::new();
+    for _ in 0..10 {
+        merged.extend(merge.filter(hidden.clone()).unwrap());
+    }
+
+    // The first 8 hidden frames fill a super frame of their own.
+    assert_eq!(merged.len(), 1);
+    assert!(merged[0].hidden);
+    let mut parser = Vp9Parser::default();
+    let mut frames = parser.parse_packet(&merged[0].data);
+    assert!(frames.is_super_frame());
+    assert_eq!(frames.by_ref().count(), 8);
+
+    // The filter keeps working with the remaining hidden frames.
+    let merged = merge.filter(displayed.clone()).unwrap();
+    assert_eq!(merged.len(), 1);
+    assert!(!merged[0].hidden);
+    let mut frames = parser.parse_packet(&merged[0].data);
+    assert!(frames.is_super_frame());
+    assert_eq!(frames.by_ref().count(), 3);
+    assert!(merge.flush().unwrap().is_empty());
+}
+
+fn displayed_frames(parser: &mut Vp9Parser, packet: &Packet) -> usize {
+    parser
+        .parse_packet(&packet.data)
+        .map(|frame| frame.unwrap())
+        .filter(|frame| frame.show_existing_frame() || frame.show_frame())
+        .count()
+}
+
+#[test]
+pub fn reorder_to_one_displayed_frame_per_packet() {
+    let packets = read_packets("tests/data/320-24-crf.ivf");
+
+    // Put the second and third packet into one super frame.
+    let mut combined = packets[1].data.clone();
+    combined.extend_from_slice(&packets[2].data);
+    combined.push(0b1101_1001);
+    for packet in &packets[1..3] {
+        combined.extend_from_slice(&u32::try_from(packet.data.len()).unwrap().to_le_bytes());
+    }
+    combined.push(0b1101_1001);
+
+    let mut input = vec![packets[0].clone()];
+    input.push(Packet {
+        timestamp: packets[1].timestamp,
+        data: combined,
+        hidden: false,
+    });
+    input.extend_from_slice(&packets[3..]);
+
+    let mut filter = RawReorder::new();
+    let mut output = Vec::new();
+    for packet in input {
+        output.extend(filter.filter(packet).unwrap());
+    }
+    output.extend(filter.flush().unwrap());
+
+    let mut parser = Vp9Parser::default();
+    assert_eq!(output.len(), packets.len());
+    for (output, packet) in output.iter().zip(packets.iter()) {
+        assert_eq!(displayed_frames(&mut parser, output), 1);
+        assert_eq!(output.timestamp, packet.timestamp);
+        assert_eq!(output.data, packet.data);
+    }
+}
+
+#[test]
+pub fn reorder_single_packet() {
+    let packets = read_packets("tests/data/320-24-crf.ivf");
+
+    // The only packet is a super frame with three displayed frames.
+    let mut combined = Vec::new();
+    for packet in &packets[..3] {
+        combined.extend_from_slice(&packet.data);
+    }
+    combined.push(0b1101_1010);
+    for packet in &packets[..3] {
+        combined.extend_from_slice(&u32::try_from(packet.data.len()).unwrap().to_le_bytes());
+    }
+    combined.push(0b1101_1010);
+
+    let mut filter = RawReorder::new();
+    let packet = Packet {
+        timestamp: 10,
+        data: combined,
+        hidden: false,
+    };
+    assert!(filter.filter(packet).unwrap().is_empty());
+
+    let output = filter.flush().unwrap();
+    assert_eq!(output.len(), 3);
+    assert_eq!(timestamps(&output), vec![10, 11, 12]);
+    for (output, packet) in output.iter().zip(packets.iter()) {
+        assert_eq!(output.data, packet.data);
+    }
+}
+
+#[test]
+pub fn reorder_hidden_frames() {
+    let packets = read_packets("tests/data/320-24-cq.ivf");
+    let mut split = SuperFrameSplit::new();
+    let mut filter = RawReorder::new();
+
+    let mut output = Vec::new();
+    let mut displayed = 0;
+    let mut parser = Vp9Parser::default();
+    for packet in packets {
+        displayed += displayed_frames(&mut parser, &packet);
+        for frame in split.filter(packet).unwrap() {
+            output.extend(filter.filter(frame).unwrap());
+        }
+    }
+    output.extend(filter.flush().unwrap());
+
+    let mut parser = Vp9Parser::default();
+    assert_eq!(output.len(), displayed);
+    assert!(output.windows(2).all(|w| w[0].timestamp <= w[1].timestamp));
+    for packet in output.iter() {
+        assert!(!packet.hidden);
+        assert_eq!(displayed_frames(&mut parser, packet), 1);
+    }
+}
+
+fn thin(packets: &[Packet], mode: ThinningMode) -> Vec<Packet> {
+    let mut filter = FrameThinning::new(mode);
+    let mut output = Vec::new();
+    for packet in packets {
+        output.extend(filter.filter(packet.clone()).unwrap());
+    }
+    output.extend(filter.flush().unwrap());
+    output
+}
+
+/// Every odd frame only refreshes slot 7, which is never referenced. Every fourth frame
+/// doesn't refresh anything. Error resilient even frames don't use the state of the previous
+/// frame.
+fn thinnable_packets(error_resilient: bool) -> Vec<Packet> {
+    let mut parser = Vp9Parser::default();
+    read_packets("tests/data/320-24-crf.ivf")
+        .into_iter()
+        .enumerate()
+        .map(|(index, packet)| {
+            let data = rewrite_packet(&mut parser, &packet.data, |_, header| {
+                assert!(!header.ref_frame_indices.contains(&7));
+                if index % 2 == 1 {
+                    header.refresh_frame_context = false;
+                    header.refresh_frame_flags = if index % 4 == 1 { 0 } else { 1 << 7 };
+                } else if index != 0 {
+                    header.error_resilient_mode = error_resilient;
+                }
+            })
+            .unwrap();
+            Packet { data, ..packet }
+        })
+        .collect()
+}
+
+fn timestamps(packets: &[Packet]) -> Vec<u64> {
+    packets.iter().map(|packet| packet.timestamp).collect()
+}
+
+#[test]
+pub fn thin_frames() {
+    let packets = thinnable_packets(true);
+
+    let non_reference = thin(&packets, ThinningMode::NonReference);
+    let expected: Vec<u64> = packets
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| index % 4 != 1)
+        .map(|(_, packet)| packet.timestamp)
+        .collect();
+    assert_eq!(timestamps(&non_reference), expected);
+
+    // The last frame that refreshes slot 7 is kept, since the end of the stream is unknown.
+    let unreferenced = thin(&packets, ThinningMode::Unreferenced);
+    let last = packets.len() - 1;
+    let expected: Vec<u64> = packets
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| index % 2 == 0 || *index == last)
+        .map(|(_, packet)| packet.timestamp)
+        .collect();
+    assert_eq!(timestamps(&unreferenced), expected);
+
+    for packet in unreferenced.iter() {
+        let original = packets
+            .iter()
+            .find(|original| original.timestamp == packet.timestamp)
+            .unwrap();
+        assert_eq!(packet.data, original.data);
+    }
+}
+
+#[test]
+pub fn thin_keeps_previous_frames() {
+    // Every frame can use the motion vectors and the segmentation map of the previous frame.
+    let packets = thinnable_packets(false);
+
+    for mode in [ThinningMode::NonReference, ThinningMode::Unreferenced] {
+        let output = thin(&packets, mode);
+        assert_eq!(timestamps(&output), timestamps(&packets), "{:?}", mode);
+    }
+}
+
+#[test]
+pub fn thin_libvpx_stream() {
+    // Two streams of libvpx, the second one starts with a key frame.
+    let mut packets = read_packets("tests/data/320-24-crf.ivf");
+    let offset = u64::try_from(packets.len()).unwrap();
+    let second: Vec<Packet> = packets
+        .iter()
+        .map(|packet| Packet {
+            timestamp: packet.timestamp + offset,
+            ..packet.clone()
+        })
+        .collect();
+    packets.extend(second);
+
+    // Every frame refreshes a reference slot and the probability context.
+    let non_reference = thin(&packets, ThinningMode::NonReference);
+    assert_eq!(timestamps(&non_reference), timestamps(&packets));
+
+    // The key frame overwrites all slots and contexts of the last frame of the first stream
+    // and doesn't use its state.
+    let unreferenced = thin(&packets, ThinningMode::Unreferenced);
+    let expected: Vec<u64> = timestamps(&packets)
+        .into_iter()
+        .filter(|timestamp| *timestamp != offset - 1)
+        .collect();
+    assert_eq!(timestamps(&unreferenced), expected);
+
+    // Without key frames in between, nothing is dropped.
+    for path in [
+        "tests/data/320-24-cq.ivf",
+        "tests/data/320-444-10bit.ivf",
+        "tests/data/320-444-12bit.ivf",
+    ] {
+        let packets = read_packets(path);
+        let output = thin(&packets, ThinningMode::Unreferenced);
+        assert_eq!(output.len(), packets.len(), "{}", path);
+    }
+}
+
+#[test]
+pub fn pad_to_constant_frame_rate() {
+    let packets = read_packets("tests/data/320-24-crf.ivf");
+    let input: Vec<Packet> = packets
+        .iter()
+        .filter(|packet| packet.timestamp != 5 && packet.timestamp != 6)
+        .cloned()
+        .collect();
+
+    let mut filter = ConstantFrameRate::new(Timebase::new(1, 24), 24, 1);
+    let mut output = Vec::new();
+    for packet in input {
+        output.extend(filter.filter(packet).unwrap());
+    }
+    output.extend(filter.flush().unwrap());
+
+    let timestamps: Vec<u64> = output.iter().map(|packet| packet.timestamp).collect();
+    let expected: Vec<u64> = packets.iter().map(|packet| packet.timestamp).collect();
+    assert_eq!(timestamps, expected);
+
+    let mut parser = Vp9Parser::default();
+    let mut last_refreshed_slot = None;
+    for packet in output.iter() {
+        let frame = parser.parse_packet(&packet.data).next().unwrap().unwrap();
+        if packet.timestamp == 5 || packet.timestamp == 6 {
+            assert_eq!(packet.data.len(), 1);
+            assert_eq!(frame.frame_to_show_map_idx(), last_refreshed_slot);
+        } else {
+            assert!(!frame.show_existing_frame());
+            let flags = frame.refresh_frame_flags();
+            last_refreshed_slot = (0..8).find(|i| (flags >> i) & 1 == 1);
+        }
+    }
+}
+
+#[test]
+pub fn pad_to_frame_rate_between_ticks() {
+    // 5 fps in a timebase of 1/30 seconds, padded to 25 fps, which is 1.2 ticks per frame.
+    let input: Vec<Packet> = read_packets("tests/data/320-24-crf.ivf")
+        .into_iter()
+        .map(|packet| Packet {
+            timestamp: packet.timestamp * 6,
+            ..packet
+        })
+        .collect();
+
+    let mut filter = ConstantFrameRate::new(Timebase::new(1, 30), 25, 1);
+    let mut output = Vec::new();
+    for packet in input.iter().cloned() {
+        output.extend(filter.filter(packet).unwrap());
+    }
+    output.extend(filter.flush().unwrap());
+
+    // Every input frame is followed by four repeated frames, and every six ticks hold exactly
+    // five frames.
+    assert_eq!(output.len(), (input.len() - 1) * 5 + 1);
+    for (packets, input) in output.chunks(5).zip(input.iter()) {
+        assert_eq!(packets[0].timestamp, input.timestamp);
+        assert_eq!(packets[0].data, input.data);
+    }
+    for pair in output.windows(2) {
+        let duration = pair[1].timestamp - pair[0].timestamp;
+        assert!(duration == 1 || duration == 2, "{}", duration);
+    }
+}
@@ -0,0 +1,42 @@
+#![cfg(feature = "bytes")]
+
+use std::{fs, io::Cursor, sync::Arc};
+
+use bytes::Bytes;
+use vp9_parser::{ivf::Ivf, Vp9Parser};
+
+#[test]
+pub fn share_bytes_frames() {
+    let data = fs::read("tests/data/320-24-cq.ivf").unwrap();
+    let mut ivf = Ivf::new(Cursor::new(&data)).unwrap();
+
+    let mut parser = Vp9Parser::default();
+    let mut bytes_parser = Vp9Parser::default();
+    let mut shared_parser = Vp9Parser::default();
+    while let Some(frame) = ivf.read_frame().unwrap() {
+        let packet = Bytes::from(frame.packet.clone());
+        let bytes_frames = bytes_parser.parse_bytes_packet(packet.clone());
+        let shared_frames = shared_parser.parse_shared_packet(Arc::from(frame.packet.as_slice()));
+        let frames: Vec<_> = parser.parse_packet(&frame.packet).collect();
+
+        for ((bytes_frame, shared_frame), frame) in
+            bytes_frames.into_iter().zip(shared_frames).zip(frames)
+        {
+            let (bytes_frame, shared_frame) = (bytes_frame.unwrap(), shared_frame.unwrap());
+            let frame = frame.unwrap();
+            for tested in [&bytes_frame, &shared_frame] {
+                assert_eq!(tested.data_bytes(), frame.data());
+                assert_eq!(tested.tile_data_bytes(), frame.tile_data());
+                assert_eq!(
+                    tested.compressed_header_bytes(),
+                    frame.compressed_header_data()
+                );
+            }
+            assert_eq!(frame.tile_data_bytes(), frame.tile_data());
+
+            // Panics if the tile data is not a part of the packet buffer.
+            let tile_data = bytes_frame.tile_data_bytes();
+            assert_eq!(packet.slice_ref(&tile_data), tile_data);
+        }
+    }
+}
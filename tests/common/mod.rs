@@ -0,0 +1,40 @@
+//! Helpers shared by the integration tests.
+
+#![allow(dead_code)]
+
+use std::{
+    fs,
+    io::{Cursor, Read},
+};
+
+use vp9_parser::{
+    bsf::Packet,
+    ivf::{Frame, Ivf, IvfError},
+};
+
+/// The test files.
+pub const FILES: [&str; 4] = [
+    "tests/data/320-24-cq.ivf",
+    "tests/data/320-24-crf.ivf",
+    "tests/data/320-444-10bit.ivf",
+    "tests/data/320-444-12bit.ivf",
+];
+
+/// Reads the remaining frames of the IVF.
+pub fn read_all<R: Read>(ivf: &mut Ivf<R>) -> Result<Vec<Frame>, IvfError> {
+    let mut frames = Vec::new();
+    while let Some(frame) = ivf.read_frame()? {
+        frames.push(frame);
+    }
+    Ok(frames)
+}
+
+/// Reads all frames of the file as packets for the bitstream filters.
+pub fn read_packets(path: &str) -> Vec<Packet> {
+    let mut ivf = Ivf::new(Cursor::new(fs::read(path).unwrap())).unwrap();
+    let mut packets = Vec::new();
+    while let Some(frame) = ivf.read_frame().unwrap() {
+        packets.push(Packet::from(frame));
+    }
+    packets
+}
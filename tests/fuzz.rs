@@ -0,0 +1,237 @@
+//! Feeds random and mutated inputs to all public entry points. None of them must panic.
+
+use std::{fs, io::Cursor};
+
+use vp9_parser::{
+    bsf::{
+        BitstreamFilter, ConstantFrameRate, FrameThinning, Packet, RawReorder, SuperFrameMerge,
+        SuperFrameSplit, ThinningMode,
+    },
+    ivf::{Ivf, IvfOptions, IvfSlice, Timebase},
+    rewrite_frame, rewrite_packet, Frame, Metadata, UncompressedHeader, Vp9Parser,
+};
+
+use common::{read_packets, FILES};
+
+mod common;
+
+/// A xorshift64* generator, so that failures can be reproduced from the seed.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Returns a value in `0..max`. `max` must not be zero.
+    fn below(&mut self, max: usize) -> usize {
+        usize::try_from(self.next() % u64::try_from(max).unwrap()).unwrap()
+    }
+
+    fn byte(&mut self) -> u8 {
+        self.next().to_le_bytes()[0]
+    }
+
+    fn bytes(&mut self, len: usize) -> Vec<u8> {
+        (0..len).map(|_| self.byte()).collect()
+    }
+
+    /// Applies one random mutation to the data.
+    fn mutate(&mut self, data: &mut Vec<u8>) {
+        match self.below(6) {
+            0 => {
+                for _ in 0..=self.below(8) {
+                    if !data.is_empty() {
+                        let index = self.below(data.len());
+                        data[index] ^= 1 << self.below(8);
+                    }
+                }
+            }
+            1 => {
+                if !data.is_empty() {
+                    let index = self.below(data.len());
+                    data[index] = self.byte();
+                }
+            }
+            2 => {
+                let len = self.below(data.len() + 1);
+                data.truncate(len);
+            }
+            3 => {
+                let index = self.below(data.len() + 1);
+                let len = self.below(16);
+                let inserted = self.bytes(len);
+                let _ = data.splice(index..index, inserted);
+            }
+            4 => {
+                let start = self.below(data.len() + 1);
+                let end = start + self.below(data.len() - start + 1);
+                let _ = data.drain(start..end);
+            }
+            _ => {
+                // Append something that looks like a super frame marker.
+                let marker = 0b1100_0000 | (self.byte() & 0b0001_1111);
+                data.push(marker);
+                if self.below(2) == 0 {
+                    let len = self.below(32);
+                    let entries = self.bytes(len);
+                    data.extend_from_slice(&entries);
+                    data.push(marker);
+                }
+            }
+        }
+    }
+}
+
+/// Calls all accessors that work on the frame data.
+fn inspect(frame: &Frame) {
+    let compressed_and_tile_size = frame.compressed_header_and_tile_data().len();
+    assert!(frame.compressed_header_data().len() <= compressed_and_tile_size);
+    assert!(frame.tile_data().len() <= compressed_and_tile_size);
+    let _ = frame.uncompressed_header_data();
+
+    let header = UncompressedHeader::from(frame);
+    let _ = format!("{:?}", header);
+    let _ = header.to_bytes();
+    let _ = rewrite_frame(frame, |header| header.refresh_frame_context = false);
+}
+
+fn parse(parser: &mut Vp9Parser, packet: &[u8]) {
+    for frame in parser.parse_packet(packet).flatten() {
+        inspect(&frame);
+    }
+}
+
+fn filter(filter: &mut dyn BitstreamFilter, packets: &[Packet]) {
+    for packet in packets {
+        let _ = filter.filter(packet.clone());
+    }
+    let _ = filter.flush();
+}
+
+#[test]
+pub fn parse_random_packets() {
+    let mut rng = Rng(0x5EED_0001);
+    let mut parser = Vp9Parser::default();
+    for _ in 0..20_000 {
+        let len = rng.below(64);
+        let mut packet = rng.bytes(len);
+        if let Some(first) = packet.first_mut() {
+            // Use a valid frame marker most of the time to get deeper into the header.
+            if rng.below(4) != 0 {
+                *first = (*first & 0b0011_1111) | 0b1000_0000;
+            }
+        }
+        parse(&mut parser, &packet);
+
+        let _ = Metadata::new(&packet);
+        let _ = IvfSlice::new(&packet).map(|slice| slice.count());
+        let _ = Ivf::new(Cursor::new(&packet));
+    }
+}
+
+#[test]
+pub fn parse_mutated_packets() {
+    let mut rng = Rng(0x5EED_0002);
+    for path in FILES {
+        let packets = read_packets(path);
+        for _ in 0..200 {
+            let mut parser = Vp9Parser::default();
+            let mut rewrite_parser = Vp9Parser::default();
+            for packet in &packets {
+                let mut data = packet.data.clone();
+                if rng.below(4) == 0 {
+                    rng.mutate(&mut data);
+                }
+                parse(&mut parser, &data);
+                let _ = rewrite_packet(&mut rewrite_parser, &data, |_, header| {
+                    header.loop_filter_level = 0;
+                });
+            }
+        }
+    }
+}
+
+#[test]
+pub fn filter_mutated_packets() {
+    let mut rng = Rng(0x5EED_0003);
+    for path in FILES {
+        let packets = read_packets(path);
+        for _ in 0..50 {
+            let mutated: Vec<Packet> = packets
+                .iter()
+                .map(|packet| {
+                    let mut packet = packet.clone();
+                    if rng.below(4) == 0 {
+                        rng.mutate(&mut packet.data);
+                        packet.timestamp = rng.next() % 64;
+                    }
+                    packet
+                })
+                .collect();
+
+            filter(&mut SuperFrameSplit::new(), &mutated);
+            filter(&mut SuperFrameMerge::new(), &mutated);
+            filter(&mut RawReorder::new(), &mutated);
+            filter(
+                &mut FrameThinning::new(ThinningMode::NonReference),
+                &mutated,
+            );
+            filter(
+                &mut FrameThinning::new(ThinningMode::Unreferenced),
+                &mutated,
+            );
+            filter(
+                &mut ConstantFrameRate::new(Timebase::new(1, 24), 24, 1),
+                &mutated,
+            );
+        }
+    }
+}
+
+#[test]
+pub fn read_mutated_ivf() {
+    let mut rng = Rng(0x5EED_0004);
+    let options = IvfOptions {
+        check_version: false,
+        check_length: false,
+        ..Default::default()
+    };
+    for path in FILES {
+        let original = fs::read(path).unwrap();
+        for _ in 0..300 {
+            let mut data = original.clone();
+            for _ in 0..=rng.below(4) {
+                rng.mutate(&mut data);
+            }
+
+            if let Ok(slice) = IvfSlice::with_options(&data, &options) {
+                let mut parser = Vp9Parser::default();
+                for frame in slice.flatten() {
+                    parse(&mut parser, frame.packet);
+                }
+            }
+
+            if let Ok(mut ivf) = Ivf::with_options(Cursor::new(&data), &options) {
+                let _ = ivf.seek_to_keyframe(rng.next() % 32);
+                let _ = ivf.seek_to_timestamp(rng.next() % 32);
+                let _ = ivf.seek_to_frame(rng.below(32));
+                while let Ok(Some(_)) = ivf.read_frame() {}
+            }
+
+            let recover = IvfOptions {
+                recover: true,
+                ..options
+            };
+            if let Ok(mut ivf) = Ivf::with_options(Cursor::new(&data), &recover) {
+                let mut parser = Vp9Parser::default();
+                while let Ok(Some(frame)) = ivf.read_frame() {
+                    parse(&mut parser, &frame.packet);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,560 @@
+//! Header-level conformance suite.
+//!
+//! Every vector `<name>.ivf` has a sidecar file `<name>.ivf.headers` with the expected header
+//! values of each frame in decoding order, one frame per line as `key=value` pairs.
+//!
+//! The vectors in `tests/data` are always checked. The libvpx `vp90-2-*` test vectors are read
+//! from the directory in `VP9_TEST_VECTORS` by an ignored test, run it with
+//! `cargo test -- --ignored`. A vector without a sidecar file is a failure. The values of a
+//! sidecar must come from libvpx, never from this parser.
+//!
+//! The synthetic vectors for resizing, odd frame sizes, intra-only frames and 4:4:0 subsampling
+//! are written with `UncompressedHeader`, so they only check that the parser and the writer
+//! agree. They don't replace the libvpx vectors.
+
+use std::{
+    env,
+    fmt::Write,
+    fs::{self, File},
+    io::{Cursor, Read},
+    path::{Path, PathBuf},
+};
+
+use vp9_parser::{
+    ivf::{self, Ivf, IvfWriter},
+    ColorSpace, Frame, FrameType, Profile, Subsampling, UncompressedHeader, Vp9Parser,
+};
+
+fn flag(value: bool) -> String {
+    u8::from(value).to_string()
+}
+
+fn list<T: ToString>(values: &[T]) -> String {
+    values
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Returns the active segment features as `<segment>:<feature>:<value>` triples, or `none`.
+fn segment_features(frame: &Frame) -> String {
+    let mut features = Vec::new();
+    for (segment, (enabled, data)) in frame
+        .segment_feature_enabled()
+        .iter()
+        .zip(frame.segment_feature_data())
+        .enumerate()
+    {
+        for (feature, (enabled, value)) in enabled.iter().zip(data).enumerate() {
+            if *enabled {
+                features.push(format!("{}:{}:{}", segment, feature, value));
+            }
+        }
+    }
+
+    if features.is_empty() {
+        "none".to_owned()
+    } else {
+        features.join(",")
+    }
+}
+
+/// Returns the header values of a frame in the sidecar format.
+fn header_values(frame: &Frame) -> Vec<(&'static str, String)> {
+    if frame.show_existing_frame() {
+        return vec![
+            ("show_existing_frame", flag(true)),
+            (
+                "frame_to_show_map_idx",
+                frame
+                    .frame_to_show_map_idx()
+                    .unwrap_or_default()
+                    .to_string(),
+            ),
+        ];
+    }
+
+    let frame_type = match frame.frame_type() {
+        FrameType::KeyFrame => "key",
+        FrameType::NonKeyFrame => "non_key",
+    };
+
+    let profile = match frame.profile() {
+        Profile::Profile0 => 0,
+        Profile::Profile1 => 1,
+        Profile::Profile2 => 2,
+        Profile::Profile3 => 3,
+        Profile::Unknown => 4,
+    };
+
+    let subsampling = match frame.subsampling() {
+        Subsampling::Yuv444 => "4:4:4",
+        Subsampling::Yuv440 => "4:4:0",
+        Subsampling::Yuv422 => "4:2:2",
+        Subsampling::Yuv420 => "4:2:0",
+    };
+
+    let mut values = vec![
+        ("show_existing_frame", flag(false)),
+        ("profile", profile.to_string()),
+        ("frame_type", frame_type.to_owned()),
+        ("show_frame", flag(frame.show_frame())),
+        ("intra_only", flag(frame.intra_only())),
+        ("error_resilient_mode", flag(frame.error_resilient_mode())),
+        (
+            "refresh_frame_flags",
+            format!("0x{:02X}", frame.refresh_frame_flags()),
+        ),
+    ];
+
+    if frame.frame_type() == FrameType::NonKeyFrame && !frame.intra_only() {
+        let [last, golden, alt] = *frame.ref_frame_indices();
+        values.push(("ref_frame_indices", format!("{},{},{}", last, golden, alt)));
+    }
+
+    values.extend([
+        ("subsampling", subsampling.to_owned()),
+        ("size", format!("{}x{}", frame.width(), frame.height())),
+        (
+            "render_size",
+            format!("{}x{}", frame.render_width(), frame.render_height()),
+        ),
+        ("tile_cols_log2", frame.tile_cols_log2().to_string()),
+        ("tile_rows_log2", frame.tile_rows_log2().to_string()),
+        ("base_q_idx", frame.base_q_idx().to_string()),
+        ("loop_filter_level", frame.loop_filter_level().to_string()),
+        (
+            "loop_filter_sharpness",
+            frame.loop_filter_sharpness().to_string(),
+        ),
+        ("segmentation_enabled", flag(frame.segmentation_enabled())),
+    ]);
+
+    values.extend([
+        (
+            "loop_filter_delta_enabled",
+            flag(frame.loop_filter_delta_enabled()),
+        ),
+        (
+            "loop_filter_ref_deltas",
+            list(frame.loop_filter_ref_deltas()),
+        ),
+        (
+            "loop_filter_mode_deltas",
+            list(frame.loop_filter_mode_deltas()),
+        ),
+    ]);
+
+    if frame.segmentation_enabled() {
+        values.extend([
+            (
+                "segmentation_update_map",
+                flag(frame.segmentation_update_map()),
+            ),
+            (
+                "segmentation_temporal_update",
+                flag(frame.segmentation_temporal_update()),
+            ),
+            ("segment_tree_probs", list(frame.segment_tree_probs())),
+            ("segment_pred_probs", list(frame.segment_pred_probs())),
+            (
+                "segmentation_update_data",
+                flag(frame.segmentation_update_data()),
+            ),
+            (
+                "segmentation_abs_or_delta_update",
+                flag(frame.segmentation_abs_or_delta_update()),
+            ),
+            ("segment_features", segment_features(frame)),
+        ]);
+    }
+
+    values
+}
+
+/// Parses the vector and returns the header values of every frame.
+fn parse_vector<R: Read>(reader: R) -> Vec<String> {
+    let mut ivf = Ivf::new(reader).unwrap();
+    let mut parser = Vp9Parser::default();
+    let mut lines = Vec::new();
+
+    while let Some(ivf_frame) = ivf.read_frame().unwrap() {
+        for frame in parser.parse_packet(&ivf_frame.packet) {
+            let frame = frame.unwrap();
+            let mut line = String::new();
+            for (key, value) in header_values(&frame) {
+                if !line.is_empty() {
+                    line.push(' ');
+                }
+                write!(line, "{}={}", key, value).unwrap();
+            }
+            lines.push(line);
+        }
+    }
+
+    lines
+}
+
+/// Compares the lines of the sidecar with the parsed values and returns the differences.
+fn compare(expected: &str, actual: &[String]) -> Vec<String> {
+    let expected: Vec<&str> = expected.lines().filter(|line| !line.is_empty()).collect();
+    let mut differences = Vec::new();
+
+    for (index, (expected, actual)) in expected.iter().zip(actual.iter()).enumerate() {
+        let actual_values: Vec<(&str, &str)> = actual
+            .split(' ')
+            .filter_map(|pair| pair.split_once('='))
+            .collect();
+
+        for (key, expected_value) in expected.split(' ').filter_map(|pair| pair.split_once('=')) {
+            let actual_value = actual_values
+                .iter()
+                .find(|(actual_key, _)| *actual_key == key)
+                .map(|(_, value)| *value);
+
+            if actual_value != Some(expected_value) {
+                differences.push(format!(
+                    "frame {}: {}: expected {}, got {}",
+                    index,
+                    key,
+                    expected_value,
+                    actual_value.unwrap_or("nothing"),
+                ));
+            }
+        }
+    }
+
+    if expected.len() != actual.len() {
+        differences.push(format!(
+            "expected {} frames, got {}",
+            expected.len(),
+            actual.len()
+        ));
+    }
+
+    differences
+}
+
+fn vectors(directory: &Path) -> Vec<PathBuf> {
+    let mut vectors: Vec<PathBuf> = fs::read_dir(directory)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "ivf"))
+        .collect();
+    vectors.sort();
+    vectors
+}
+
+fn sidecar(vector: &Path) -> PathBuf {
+    let mut path = vector.to_path_buf().into_os_string();
+    path.push(".headers");
+    PathBuf::from(path)
+}
+
+fn check_directory(directory: &Path) -> Vec<String> {
+    let mut failures = Vec::new();
+
+    for vector in vectors(directory) {
+        let sidecar = sidecar(&vector);
+        if !sidecar.exists() {
+            failures.push(format!(
+                "{}: missing sidecar {}",
+                vector.display(),
+                sidecar.display()
+            ));
+            continue;
+        }
+
+        let actual = parse_vector(File::open(&vector).unwrap());
+
+        let expected = fs::read_to_string(&sidecar).unwrap();
+        for difference in compare(&expected, &actual) {
+            failures.push(format!("{}: {}", vector.display(), difference));
+        }
+    }
+
+    failures
+}
+
+/// Returns the headers of the first key frame and the first inter frame of a test file, which
+/// are used as templates for the synthetic vectors.
+fn templates() -> (UncompressedHeader, UncompressedHeader) {
+    let mut ivf = Ivf::new(File::open("tests/data/320-24-crf.ivf").unwrap()).unwrap();
+    let mut parser = Vp9Parser::default();
+
+    let mut headers = Vec::new();
+    while headers.len() < 2 {
+        let ivf_frame = ivf.read_frame().unwrap().unwrap();
+        for frame in parser.parse_packet(&ivf_frame.packet) {
+            headers.push(UncompressedHeader::from(&frame.unwrap()));
+        }
+    }
+
+    let inter = headers.pop().unwrap();
+    let key = headers.pop().unwrap();
+    assert_eq!(key.frame_type, FrameType::KeyFrame);
+    assert_eq!(inter.frame_type, FrameType::NonKeyFrame);
+
+    (key, inter)
+}
+
+/// Writes the headers as an IVF. Every frame is followed by zeroed compressed header and tile
+/// data, which is enough for the header parser.
+fn synthetic_vector(headers: &[UncompressedHeader]) -> Vec<u8> {
+    let mut writer = IvfWriter::new(Cursor::new(Vec::new()), 0, 0, 30, 1).unwrap();
+
+    for (timestamp, header) in (0..).zip(headers) {
+        let mut packet = header.to_bytes().unwrap();
+        packet.resize(packet.len() + header.header_size_in_bytes + 1, 0);
+        writer
+            .write_frame(&ivf::Frame { timestamp, packet })
+            .unwrap();
+    }
+
+    writer.finish().unwrap().into_inner()
+}
+
+fn check_synthetic_vector(name: &str, headers: &[UncompressedHeader], expected: &str) {
+    let actual = parse_vector(Cursor::new(synthetic_vector(headers)));
+    let failures = compare(expected, &actual);
+    assert!(failures.is_empty(), "{}: {}", name, failures.join("\n"));
+}
+
+#[test]
+pub fn headers_of_resized_frames() {
+    let (mut key, mut inter) = templates();
+    key.width = 352;
+    key.height = 288;
+    key.render_and_frame_size_different = false;
+
+    inter.refresh_frame_flags = 0x01;
+    inter.ref_frame_indices = [0, 1, 2];
+    inter.render_and_frame_size_different = false;
+
+    let mut downscaled = inter.clone();
+    downscaled.found_ref = None;
+    downscaled.width = 176;
+    downscaled.height = 144;
+
+    let mut last = inter.clone();
+    last.found_ref = Some(0);
+
+    let mut golden = inter;
+    golden.found_ref = Some(1);
+
+    check_synthetic_vector(
+        "resize",
+        &[key, downscaled, last, golden],
+        "frame_type=key size=352x288 render_size=352x288
+         frame_type=non_key refresh_frame_flags=0x01 size=176x144 render_size=176x144
+         frame_type=non_key refresh_frame_flags=0x01 size=176x144 render_size=176x144
+         frame_type=non_key refresh_frame_flags=0x01 size=352x288 render_size=352x288",
+    );
+}
+
+#[test]
+pub fn headers_of_odd_sized_frames() {
+    let (mut key, mut inter) = templates();
+    key.width = 33;
+    key.height = 17;
+    key.render_and_frame_size_different = true;
+    key.render_width = 31;
+    key.render_height = 15;
+
+    inter.found_ref = Some(0);
+    inter.render_and_frame_size_different = false;
+
+    check_synthetic_vector(
+        "odd size",
+        &[key, inter],
+        "frame_type=key size=33x17 render_size=31x15 tile_cols_log2=0
+         frame_type=non_key size=33x17 render_size=33x17 tile_cols_log2=0",
+    );
+}
+
+#[test]
+pub fn headers_of_intra_only_frames() {
+    let (mut key, inter) = templates();
+    key.width = 64;
+    key.height = 48;
+    key.render_and_frame_size_different = false;
+
+    let mut intra_only = inter.clone();
+    intra_only.show_frame = false;
+    intra_only.intra_only = true;
+    intra_only.refresh_frame_flags = 0x02;
+    intra_only.width = 48;
+    intra_only.height = 32;
+    intra_only.render_and_frame_size_different = false;
+
+    let mut golden = inter;
+    golden.ref_frame_indices = [0, 1, 2];
+    golden.found_ref = Some(1);
+    golden.render_and_frame_size_different = false;
+
+    check_synthetic_vector(
+        "intra-only",
+        &[key, intra_only, golden],
+        "frame_type=key show_frame=1 intra_only=0 refresh_frame_flags=0xFF size=64x48
+         frame_type=non_key show_frame=0 intra_only=1 refresh_frame_flags=0x02 profile=0 \
+         subsampling=4:2:0 size=48x32 render_size=48x32
+         frame_type=non_key show_frame=1 intra_only=0 ref_frame_indices=0,1,2 size=48x32",
+    );
+}
+
+#[test]
+pub fn headers_of_440_frames() {
+    let (mut key, mut inter) = templates();
+    key.profile = Profile::Profile1;
+    key.color_space = ColorSpace::Bt709;
+    key.subsampling_x = false;
+    key.subsampling_y = true;
+
+    inter.profile = Profile::Profile1;
+
+    check_synthetic_vector(
+        "4:4:0",
+        &[key, inter],
+        "frame_type=key profile=1 subsampling=4:4:0 size=320x180
+         frame_type=non_key profile=1 subsampling=4:4:0 size=320x180",
+    );
+}
+
+#[test]
+pub fn headers_of_wide_frames() {
+    let (mut key, _) = templates();
+    key.render_and_frame_size_different = false;
+
+    // 1024 pixels are 16 superblocks, which allow up to 4 tile columns.
+    let mut four_tiles = key.clone();
+    four_tiles.width = 1024;
+    four_tiles.height = 576;
+    four_tiles.tile_cols_log2 = 2;
+    four_tiles.tile_rows_log2 = 1;
+
+    // 257 superblocks need at least 8 tile columns.
+    let mut widest = key.clone();
+    widest.width = 16448;
+    widest.height = 64;
+    widest.tile_cols_log2 = 5;
+    widest.tile_rows_log2 = 0;
+
+    let mut narrow = key;
+    narrow.tile_cols_log2 = 0;
+    narrow.tile_rows_log2 = 0;
+
+    check_synthetic_vector(
+        "wide frames",
+        &[four_tiles, narrow.clone(), widest, narrow],
+        "size=1024x576 tile_cols_log2=2 tile_rows_log2=1
+         size=320x180 tile_cols_log2=0 tile_rows_log2=0
+         size=16448x64 tile_cols_log2=5 tile_rows_log2=0
+         size=320x180 tile_cols_log2=0 tile_rows_log2=0",
+    );
+}
+
+#[test]
+pub fn segmentation_state_between_frames() {
+    const ALT_Q: usize = 0;
+    const ALT_L: usize = 1;
+
+    let (mut key, mut inter) = templates();
+    key.segmentation_enabled = true;
+    key.segmentation_update_map = true;
+    key.segment_tree_probs = [128; 7];
+    key.segmentation_update_data = true;
+    key.segment_feature_enabled[1][ALT_Q] = true;
+    key.segment_feature_data[1][ALT_Q] = -10;
+
+    inter.segmentation_enabled = true;
+
+    let mut temporal_update = inter.clone();
+    temporal_update.segmentation_update_map = true;
+    temporal_update.segment_tree_probs = [128; 7];
+    temporal_update.segmentation_temporal_update = true;
+    temporal_update.segment_pred_probs = [128; 3];
+
+    let mut new_data = inter.clone();
+    new_data.segmentation_update_data = true;
+    new_data.segment_feature_enabled[2][ALT_L] = true;
+    new_data.segment_feature_data[2][ALT_L] = 5;
+
+    let mut plain_key = key.clone();
+    plain_key.segmentation_enabled = false;
+
+    let vector = synthetic_vector(&[key, temporal_update, inter.clone(), new_data, plain_key]);
+    let failures = compare(
+        "segmentation_enabled=1 segmentation_update_map=1 segmentation_update_data=1 \
+         segmentation_abs_or_delta_update=0 segment_features=1:0:-10
+         segmentation_enabled=1 segmentation_temporal_update=1 segment_pred_probs=128,128,128 \
+         segment_features=1:0:-10
+         segmentation_enabled=1 segmentation_update_map=0 segment_features=1:0:-10
+         segmentation_enabled=1 segmentation_update_data=1 segment_features=2:1:5
+         segmentation_enabled=0",
+        &parse_vector(Cursor::new(&vector)),
+    );
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+
+    let mut ivf = Ivf::new(Cursor::new(vector)).unwrap();
+    let mut parser = Vp9Parser::default();
+    let mut frames = Vec::new();
+    while let Some(ivf_frame) = ivf.read_frame().unwrap() {
+        for frame in parser.parse_packet(&ivf_frame.packet) {
+            let frame = frame.unwrap();
+            frames.push((
+                frame.segmentation_update_map(),
+                frame.segmentation_temporal_update(),
+                frame.segmentation_update_data(),
+                *frame.segment_feature_enabled(),
+                *frame.segment_feature_data(),
+            ));
+        }
+    }
+    assert_eq!(frames.len(), 5);
+
+    let mut enabled = [[false; 4]; 8];
+    let mut data = [[0; 4]; 8];
+    enabled[1][ALT_Q] = true;
+    data[1][ALT_Q] = -10;
+    assert_eq!(frames[0], (true, false, true, enabled, data));
+    assert_eq!(frames[1], (true, true, false, enabled, data));
+
+    // Flags that are not coded must not leak from the previous frame, the features persist.
+    assert_eq!(frames[2], (false, false, false, enabled, data));
+
+    // New segmentation data replaces the features of all segments.
+    let mut enabled = [[false; 4]; 8];
+    let mut data = [[0; 4]; 8];
+    enabled[2][ALT_L] = true;
+    data[2][ALT_L] = 5;
+    assert_eq!(frames[3], (false, false, true, enabled, data));
+
+    // Key frames reset the features.
+    assert_eq!(
+        frames[4],
+        (false, false, false, [[false; 4]; 8], [[0; 4]; 8])
+    );
+}
+
+#[test]
+pub fn headers_of_test_data() {
+    let failures = check_directory(Path::new("tests/data"));
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+#[test]
+#[ignore = "needs the libvpx test vectors in VP9_TEST_VECTORS"]
+pub fn headers_of_libvpx_test_vectors() {
+    let directory = PathBuf::from(
+        env::var_os("VP9_TEST_VECTORS").expect("VP9_TEST_VECTORS must point to the test vectors"),
+    );
+    assert!(
+        !vectors(&directory).is_empty(),
+        "no vectors in {}",
+        directory.display()
+    );
+
+    let failures = check_directory(&directory);
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
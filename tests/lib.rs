@@ -1,10 +1,27 @@
-use std::fs::File;
+use std::{
+    fs::{self, File},
+    io::{Cursor, Read, Seek, SeekFrom},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
 use vp9_parser::{
-    ivf::{Frame, Ivf},
-    ColorDepth, ColorRange, ColorSpace, FrameType, Profile, Subsampling, Vp9Parser,
+    bsf::show_existing_frame,
+    ivf::{
+        FourCc, Frame, HeaderWarning, Ivf, IvfError, IvfOptions, IvfSlice, IvfWriter, StreamFrame,
+        Timebase, TimestampAnomaly, Vp9Stream,
+    },
+    rewrite_packet, ColorDepth, ColorRange, ColorSpace, FrameHeader, FrameType, Limit, Limits,
+    Profile, Subsampling, UncompressedHeader, Vp9Parser, Vp9ParserError,
 };
 
+use common::{read_all, FILES};
+
+mod common;
+
 #[test]
 pub fn parse_ivf() {
     let file = File::open("tests/data/320-24-crf.ivf").unwrap();
@@ -142,3 +159,843 @@ pub fn parse_vp9_12bit() {
         }
     }
 }
+
+#[test]
+pub fn write_uncompressed_headers() {
+    for path in FILES {
+        let file = File::open(path).unwrap();
+        let mut ivf = Ivf::new(file).unwrap();
+        let mut parser = Vp9Parser::default();
+
+        while let Some(ivf_frame) = ivf.read_frame().unwrap() {
+            for frame in parser.parse_packet(&ivf_frame.packet) {
+                let frame = frame.unwrap();
+                let header = UncompressedHeader::from(&frame);
+                assert_eq!(
+                    header.to_bytes().unwrap(),
+                    frame.uncompressed_header_data(),
+                    "{}",
+                    path
+                );
+            }
+        }
+    }
+}
+
+#[test]
+pub fn rewrite_packets() {
+    let file = File::open("tests/data/320-24-cq.ivf").unwrap();
+    let mut ivf = Ivf::new(file).unwrap();
+    let mut parser = Vp9Parser::default();
+    let mut identity_parser = Vp9Parser::default();
+    let mut rewritten_parser = Vp9Parser::default();
+    let mut original_parser = Vp9Parser::default();
+
+    while let Some(ivf_frame) = ivf.read_frame().unwrap() {
+        let packet = ivf_frame.packet;
+
+        let identity = rewrite_packet(&mut identity_parser, &packet, |_, _| {}).unwrap();
+        assert_eq!(identity, packet);
+
+        let rewritten = rewrite_packet(&mut parser, &packet, |_, header| {
+            header.color_space = ColorSpace::Bt709;
+            header.render_and_frame_size_different = true;
+            header.render_width = 640;
+            header.render_height = 360;
+        })
+        .unwrap();
+
+        let original = original_parser
+            .parse_packet(&packet)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let frames = rewritten_parser
+            .parse_packet(&rewritten)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert_eq!(frames.len(), original.len());
+        for (frame, original) in frames.iter().zip(original.iter()) {
+            if frame.show_existing_frame() {
+                continue;
+            }
+            if frame.frame_type() == FrameType::KeyFrame {
+                assert_eq!(frame.color_space(), ColorSpace::Bt709);
+            }
+            assert_eq!(frame.render_width(), 640);
+            assert_eq!(frame.render_height(), 360);
+            assert_eq!(frame.width(), original.width());
+            assert_eq!(
+                frame.compressed_header_and_tile_data(),
+                original.compressed_header_and_tile_data()
+            );
+        }
+    }
+}
+
+#[test]
+pub fn write_ivf() {
+    let original = fs::read("tests/data/320-24-crf.ivf").unwrap();
+    let mut ivf = Ivf::new(Cursor::new(&original)).unwrap();
+    let mut writer = IvfWriter::new(
+        Cursor::new(Vec::new()),
+        ivf.width(),
+        ivf.height(),
+        ivf.frame_rate_rate(),
+        ivf.frame_rate_scale(),
+    )
+    .unwrap();
+
+    while let Some(frame) = ivf.read_frame().unwrap() {
+        writer.write_frame(&frame).unwrap();
+    }
+
+    let written = writer.finish().unwrap().into_inner();
+    assert_eq!(written, original);
+}
+
+#[test]
+pub fn seek_ivf() {
+    let file = File::open("tests/data/320-24-cq.ivf").unwrap();
+    let mut ivf = Ivf::new(file).unwrap();
+
+    let mut frames = Vec::new();
+    while let Some(frame) = ivf.read_frame().unwrap() {
+        frames.push(frame);
+    }
+
+    let index = ivf.index().unwrap().to_vec();
+    assert_eq!(index.len(), frames.len());
+    assert_eq!(index[0].offset, 32);
+    for (entry, frame) in index.iter().zip(frames.iter()) {
+        assert_eq!(entry.timestamp, frame.timestamp);
+        assert_eq!(entry.size, u32::try_from(frame.packet.len()).unwrap());
+    }
+
+    ivf.seek_to_frame(5).unwrap();
+    assert_eq!(ivf.read_frame().unwrap().unwrap().packet, frames[5].packet);
+
+    let number = ivf.seek_to_timestamp(frames[7].timestamp).unwrap();
+    assert_eq!(number, 7);
+    assert_eq!(ivf.read_frame().unwrap().unwrap().packet, frames[7].packet);
+
+    let number = ivf.seek_to_keyframe(frames[10].timestamp).unwrap();
+    assert_eq!(number, 0);
+    let packet = ivf.read_frame().unwrap().unwrap().packet;
+    let frame = Vp9Parser::default()
+        .parse_packet(&packet)
+        .next()
+        .unwrap()
+        .unwrap();
+    assert_eq!(frame.frame_type(), FrameType::KeyFrame);
+
+    assert!(matches!(
+        ivf.seek_to_frame(frames.len()),
+        Err(IvfError::FrameNotFound(_))
+    ));
+}
+
+#[test]
+pub fn seek_to_keyframe_over_corrupt_packets() {
+    let mut ivf = Ivf::new(File::open("tests/data/320-24-crf.ivf").unwrap()).unwrap();
+    let mut writer = IvfWriter::new(Cursor::new(Vec::new()), 320, 180, 24, 1).unwrap();
+    while let Some(mut frame) = ivf.read_frame().unwrap() {
+        match frame.timestamp {
+            4 => frame.packet.truncate(1),
+            6 => frame.packet = vec![0xFF; 16],
+            _ => {}
+        }
+        writer.write_frame(&frame).unwrap();
+    }
+    let data = writer.finish().unwrap().into_inner();
+
+    let mut ivf = Ivf::new(Cursor::new(data)).unwrap();
+    assert_eq!(ivf.seek_to_keyframe(10).unwrap(), 0);
+    assert_eq!(ivf.seek_to_keyframe(5).unwrap(), 0);
+}
+
+/// A reader that counts the bytes that are read through it.
+struct CountingReader<R> {
+    inner: R,
+    count: Arc<AtomicUsize>,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        let _ = self.count.fetch_add(read, Ordering::Relaxed);
+        Ok(read)
+    }
+}
+
+impl<R: Seek> Seek for CountingReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+#[test]
+pub fn seek_to_keyframe_probes_one_byte_per_frame() {
+    let data = fs::read("tests/data/320-24-crf.ivf").unwrap();
+    let count = Arc::new(AtomicUsize::new(0));
+    let reader = CountingReader {
+        inner: Cursor::new(data),
+        count: count.clone(),
+    };
+    let mut ivf = Ivf::new(reader).unwrap();
+    let frames = ivf.index().unwrap().len();
+
+    count.store(0, Ordering::Relaxed);
+    assert_eq!(ivf.seek_to_keyframe(u64::MAX).unwrap(), 0);
+    assert_eq!(count.load(Ordering::Relaxed), frames - 1);
+}
+
+#[test]
+pub fn parse_ivf_slice() {
+    let data = fs::read("tests/data/320-24-cq.ivf").unwrap();
+    let mut ivf = Ivf::new(Cursor::new(&data)).unwrap();
+    let slice = IvfSlice::new(&data).unwrap();
+
+    assert_eq!(slice.width(), ivf.width());
+    assert_eq!(slice.height(), ivf.height());
+    assert_eq!(slice.frame_count(), ivf.frame_count());
+
+    let mut parser = Vp9Parser::default();
+    let mut buffer = Vec::new();
+    let mut count = 0;
+    for frame in slice {
+        let frame = frame.unwrap();
+        let timestamp = ivf.read_frame_into(&mut buffer).unwrap().unwrap();
+        assert_eq!(frame.timestamp, timestamp);
+        assert_eq!(frame.packet, buffer.as_slice());
+
+        for vp9_frame in parser.parse_packet(frame.packet) {
+            let vp9_frame = vp9_frame.unwrap();
+            assert!(data
+                .as_ptr_range()
+                .contains(&vp9_frame.tile_data().as_ptr()));
+        }
+        count += 1;
+    }
+
+    assert_eq!(count, ivf.frame_count());
+    assert!(ivf.read_frame_into(&mut buffer).unwrap().is_none());
+
+    let mut truncated = IvfSlice::new(&data[..data.len() - 1]).unwrap();
+    assert!(truncated.by_ref().any(|frame| frame.is_err()));
+    assert!(truncated.next().is_none());
+}
+
+fn first_error(path: &str, limits: Limits) -> Option<Vp9ParserError> {
+    let mut ivf = Ivf::new(File::open(path).unwrap()).unwrap();
+    let mut parser = Vp9Parser::with_limits(limits);
+    while let Some(frame) = ivf.read_frame().unwrap() {
+        if let Some(Err(err)) = parser
+            .parse_packet(&frame.packet)
+            .find(|frame| frame.is_err())
+        {
+            return Some(err);
+        }
+    }
+    None
+}
+
+#[test]
+pub fn enforce_limits() {
+    assert!(first_error("tests/data/320-24-cq.ivf", Limits::default()).is_none());
+
+    let limits = Limits {
+        max_width: 100,
+        ..Default::default()
+    };
+    assert!(matches!(
+        first_error("tests/data/320-24-crf.ivf", limits),
+        Some(Vp9ParserError::LimitExceeded(Limit::Width(320)))
+    ));
+
+    let limits = Limits {
+        max_area: 320 * 180 - 1,
+        ..Default::default()
+    };
+    assert!(matches!(
+        first_error("tests/data/320-24-crf.ivf", limits),
+        Some(Vp9ParserError::LimitExceeded(Limit::Area(57600)))
+    ));
+
+    let limits = Limits {
+        max_tiles: 0,
+        ..Default::default()
+    };
+    assert!(matches!(
+        first_error("tests/data/320-24-crf.ivf", limits),
+        Some(Vp9ParserError::LimitExceeded(Limit::Tiles(1)))
+    ));
+
+    let limits = Limits {
+        max_frames_per_super_frame: 1,
+        ..Default::default()
+    };
+    assert!(matches!(
+        first_error("tests/data/320-24-cq.ivf", limits),
+        Some(Vp9ParserError::LimitExceeded(Limit::FramesPerSuperFrame(2)))
+    ));
+}
+
+#[test]
+pub fn enforce_ivf_limits() {
+    let mut data = fs::read("tests/data/320-24-crf.ivf").unwrap()[..32].to_vec();
+    data.extend_from_slice(&[0xFF, 0xFF, 0xFF, 0xFF, 0, 0, 0, 0, 0, 0, 0, 0, 0x82]);
+
+    let mut ivf = Ivf::new(Cursor::new(&data)).unwrap();
+    assert!(matches!(
+        ivf.read_frame(),
+        Err(IvfError::LimitExceeded(Limit::PacketSize(0xFFFF_FFFF)))
+    ));
+
+    let mut slice = IvfSlice::new(&data).unwrap();
+    assert!(matches!(
+        slice.read_frame(),
+        Err(IvfError::LimitExceeded(Limit::PacketSize(0xFFFF_FFFF)))
+    ));
+
+    // Without a packet size limit, only the data of the file is allocated.
+    let options = IvfOptions {
+        limits: Limits {
+            max_packet_size: usize::MAX,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut ivf = Ivf::with_options(Cursor::new(&data), &options).unwrap();
+    assert!(matches!(
+        ivf.read_frame(),
+        Err(IvfError::UnexpectedFileEnding)
+    ));
+}
+
+#[test]
+pub fn recover_truncated_ivf() {
+    let data = fs::read("tests/data/320-24-crf.ivf").unwrap();
+    let frames = read_all(&mut Ivf::new(Cursor::new(&data)).unwrap()).unwrap();
+    let last = frames.last().unwrap();
+    let options = IvfOptions {
+        recover: true,
+        ..Default::default()
+    };
+
+    // Truncated inside the data of the last frame.
+    let truncated = &data[..data.len() - last.packet.len() / 2];
+    let mut ivf = Ivf::new(Cursor::new(truncated)).unwrap();
+    assert!(matches!(
+        read_all(&mut ivf),
+        Err(IvfError::UnexpectedFileEnding)
+    ));
+
+    let mut ivf = Ivf::with_options(Cursor::new(truncated), &options).unwrap();
+    let recovered = read_all(&mut ivf).unwrap();
+    assert!(ivf.is_truncated());
+    assert_eq!(recovered.len(), frames.len());
+    let partial = recovered.last().unwrap();
+    assert_eq!(partial.timestamp, last.timestamp);
+    assert!(last.packet.starts_with(&partial.packet));
+    assert!(partial.packet.len() < last.packet.len());
+
+    // Truncated inside the header of the last frame.
+    let truncated = &data[..data.len() - last.packet.len() - 5];
+    let mut ivf = Ivf::new(Cursor::new(truncated)).unwrap();
+    assert!(matches!(
+        read_all(&mut ivf),
+        Err(IvfError::UnexpectedFileEnding)
+    ));
+
+    let mut ivf = Ivf::with_options(Cursor::new(truncated), &options).unwrap();
+    assert_eq!(read_all(&mut ivf).unwrap().len(), frames.len() - 1);
+    assert!(ivf.is_truncated());
+
+    // The slice reader handles trailing bytes the same way. Without recovery, a partial size
+    // field ends the file like it always did, a partial timestamp is an error.
+    for trailing in [1, 3, 4, 11] {
+        let truncated = &data[..data.len() - last.packet.len() - 12 + trailing];
+        let mut ivf = Ivf::new(Cursor::new(truncated)).unwrap();
+        let slice = IvfSlice::new(truncated).unwrap();
+        if trailing < 4 {
+            assert_eq!(read_all(&mut ivf).unwrap().len(), frames.len() - 1);
+            assert_eq!(slice.count(), frames.len() - 1);
+        } else {
+            assert!(matches!(
+                read_all(&mut ivf),
+                Err(IvfError::UnexpectedFileEnding)
+            ));
+            assert!(matches!(
+                slice.collect::<Result<Vec<_>, _>>(),
+                Err(IvfError::UnexpectedFileEnding)
+            ));
+        }
+
+        let mut slice = IvfSlice::with_options(truncated, &options).unwrap();
+        assert_eq!(slice.by_ref().count(), frames.len() - 1);
+        assert!(slice.is_truncated());
+    }
+
+    let truncated = &data[..data.len() - last.packet.len() / 2];
+    let mut slice = IvfSlice::with_options(truncated, &options).unwrap();
+    let recovered = slice.by_ref().collect::<Result<Vec<_>, _>>().unwrap();
+    assert!(slice.is_truncated());
+    assert_eq!(recovered.len(), frames.len());
+    assert!(last.packet.starts_with(recovered.last().unwrap().packet));
+
+    // A clean end is not truncated.
+    let mut ivf = Ivf::with_options(Cursor::new(&data), &options).unwrap();
+    assert_eq!(read_all(&mut ivf).unwrap().len(), frames.len());
+    assert!(!ivf.is_truncated());
+    assert_eq!(ivf.skipped_bytes(), 0);
+}
+
+#[test]
+pub fn recover_corrupted_ivf() {
+    let mut data = fs::read("tests/data/320-24-crf.ivf").unwrap();
+    let frames = read_all(&mut Ivf::new(Cursor::new(&data)).unwrap()).unwrap();
+
+    // Corrupt the size of the sixth frame.
+    let offset = 32
+        + frames[..5]
+            .iter()
+            .map(|frame| 12 + frame.packet.len())
+            .sum::<usize>();
+    data[offset..offset + 4].copy_from_slice(&[0xFF; 4]);
+
+    let mut ivf = Ivf::new(Cursor::new(&data)).unwrap();
+    assert!(matches!(
+        read_all(&mut ivf),
+        Err(IvfError::LimitExceeded(Limit::PacketSize(0xFFFF_FFFF)))
+    ));
+
+    let options = IvfOptions {
+        recover: true,
+        ..Default::default()
+    };
+    let mut ivf = Ivf::with_options(Cursor::new(&data), &options).unwrap();
+    let recovered = read_all(&mut ivf).unwrap();
+    assert!(!ivf.is_truncated());
+    assert_eq!(
+        ivf.skipped_bytes(),
+        u64::try_from(12 + frames[5].packet.len()).unwrap()
+    );
+
+    let expected: Vec<u64> = frames
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| *i != 5)
+        .map(|(_, frame)| frame.timestamp)
+        .collect();
+    let timestamps: Vec<u64> = recovered.iter().map(|frame| frame.timestamp).collect();
+    assert_eq!(timestamps, expected);
+
+    let mut slice = IvfSlice::with_options(&data, &options).unwrap();
+    let recovered = slice.by_ref().collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(slice.skipped_bytes(), ivf.skipped_bytes());
+    let timestamps: Vec<u64> = recovered.iter().map(|frame| frame.timestamp).collect();
+    assert_eq!(timestamps, expected);
+}
+
+/// Writes the frames of the test file with the given timestamps.
+fn with_timestamps(timestamps: &[u64]) -> Vec<u8> {
+    let mut ivf = Ivf::new(File::open("tests/data/320-24-crf.ivf").unwrap()).unwrap();
+    let mut writer = IvfWriter::new(Cursor::new(Vec::new()), 320, 180, 24, 1).unwrap();
+    for timestamp in timestamps {
+        let frame = ivf.read_frame().unwrap().unwrap();
+        writer
+            .write_frame(&Frame {
+                timestamp: *timestamp,
+                packet: frame.packet,
+            })
+            .unwrap();
+    }
+    writer.finish().unwrap().into_inner()
+}
+
+#[test]
+pub fn recover_ivf_with_unusual_timestamps() {
+    let options = IvfOptions {
+        recover: true,
+        ..Default::default()
+    };
+
+    let large: Vec<u64> = (0..8).map(|i| (1 << 40) + i).collect();
+    let repeated = [0, 1, 1, 1, 3, 2, 4, 5];
+    for timestamps in [&large[..], &repeated[..]] {
+        let data = with_timestamps(timestamps);
+
+        let mut ivf = Ivf::with_options(Cursor::new(&data), &options).unwrap();
+        let frames = read_all(&mut ivf).unwrap();
+        assert_eq!(ivf.skipped_bytes(), 0);
+        let read: Vec<u64> = frames.iter().map(|frame| frame.timestamp).collect();
+        assert_eq!(read, timestamps);
+
+        let mut slice = IvfSlice::with_options(&data, &options).unwrap();
+        let frames = slice.by_ref().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(slice.skipped_bytes(), 0);
+        let read: Vec<u64> = frames.iter().map(|frame| frame.timestamp).collect();
+        assert_eq!(read, timestamps);
+    }
+}
+
+struct FailingReader<R> {
+    reader: R,
+    remaining: usize,
+}
+
+impl<R: std::io::Read> std::io::Read for FailingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.remaining == 0 {
+            return Err(std::io::Error::other("device unplugged"));
+        }
+        let len = buf.len().min(self.remaining);
+        let read = self.reader.read(&mut buf[..len])?;
+        self.remaining -= read;
+        Ok(read)
+    }
+}
+
+#[test]
+pub fn propagate_ivf_io_errors() {
+    let data = fs::read("tests/data/320-24-crf.ivf").unwrap();
+    for recover in [false, true] {
+        let reader = FailingReader {
+            reader: Cursor::new(&data),
+            remaining: 32,
+        };
+        let options = IvfOptions {
+            recover,
+            ..Default::default()
+        };
+        let mut ivf = Ivf::with_options(reader, &options).unwrap();
+        assert!(matches!(ivf.read_frame(), Err(IvfError::IoError(_))));
+    }
+}
+
+#[test]
+pub fn convert_ivf_timestamps() {
+    let ivf = Ivf::new(File::open("tests/data/320-24-crf.ivf").unwrap()).unwrap();
+    let timebase = ivf.timebase();
+    assert_eq!(timebase, Timebase::new(1, 24));
+    assert_eq!(timebase.to_duration(12), Some(Duration::from_millis(500)));
+    assert_eq!(timebase.rescale(12, Timebase::new(1, 90000)), Some(45000));
+}
+
+#[test]
+pub fn regenerate_broken_timestamps() {
+    let mut data = fs::read("tests/data/320-24-crf.ivf").unwrap();
+    let frames = read_all(&mut Ivf::new(Cursor::new(&data)).unwrap()).unwrap();
+
+    // Overwrite the timestamp of the fourth frame.
+    let offset = 32
+        + frames[..3]
+            .iter()
+            .map(|frame| 12 + frame.packet.len())
+            .sum::<usize>();
+    data[offset + 4..offset + 12].copy_from_slice(&100u64.to_le_bytes());
+
+    let mut ivf = Ivf::new(Cursor::new(&data)).unwrap();
+    assert_eq!(
+        ivf.timestamp_anomalies(1).unwrap(),
+        [
+            TimestampAnomaly::Gap {
+                frame: 3,
+                timestamp: 100,
+                previous: 2
+            },
+            TimestampAnomaly::NonMonotonic {
+                frame: 4,
+                timestamp: 4,
+                previous: 100
+            },
+        ]
+    );
+
+    let options = IvfOptions {
+        regenerate_timestamps: Some(2),
+        ..Default::default()
+    };
+    let expected: Vec<u64> = (0..24).map(|number| number * 2).collect();
+
+    let mut ivf = Ivf::with_options(Cursor::new(&data), &options).unwrap();
+    assert!(ivf.timestamp_anomalies(2).unwrap().is_empty());
+    let timestamps: Vec<u64> = read_all(&mut ivf)
+        .unwrap()
+        .iter()
+        .map(|frame| frame.timestamp)
+        .collect();
+    assert_eq!(timestamps, expected);
+
+    assert_eq!(ivf.seek_to_timestamp(7).unwrap(), 3);
+    assert_eq!(ivf.read_frame().unwrap().unwrap().timestamp, 6);
+
+    let slice = IvfSlice::with_options(&data, &options).unwrap();
+    let timestamps: Vec<u64> = slice.map(|frame| frame.unwrap().timestamp).collect();
+    assert_eq!(timestamps, expected);
+}
+
+#[test]
+pub fn validate_ivf_header() {
+    let original = fs::read("tests/data/320-24-crf.ivf").unwrap();
+    let report = Ivf::new(Cursor::new(&original))
+        .unwrap()
+        .validate_header()
+        .unwrap();
+    assert!(report.is_valid());
+    assert_eq!(report.frame_count(), 24);
+    assert_eq!(report.width(), Some(320));
+    assert_eq!(report.height(), Some(180));
+    assert_eq!(report.max_width(), Some(320));
+    assert_eq!(report.max_height(), Some(180));
+
+    let mut data = original.clone();
+    data[12..14].copy_from_slice(&640u16.to_le_bytes());
+    data[24..28].copy_from_slice(&0u32.to_le_bytes());
+    let expected = [
+        HeaderWarning::FrameCount {
+            header: 0,
+            actual: 24,
+        },
+        HeaderWarning::FrameSize {
+            header_width: 640,
+            header_height: 180,
+            width: 320,
+            height: 180,
+        },
+    ];
+
+    let frames = read_all(&mut Ivf::new(Cursor::new(&original)).unwrap()).unwrap();
+    let mut ivf = Ivf::new(Cursor::new(&data)).unwrap();
+    let _ = ivf.read_frame().unwrap();
+    let (corrected, report) = ivf.write_corrected(Cursor::new(Vec::new())).unwrap();
+    assert_eq!(report.warnings(), expected);
+    assert_eq!(corrected.into_inner(), original);
+    // The position of the reader is kept.
+    assert_eq!(ivf.read_frame().unwrap().unwrap().packet, frames[1].packet);
+
+    let slice = IvfSlice::new(&data).unwrap();
+    let (corrected, report) = slice.write_corrected(Cursor::new(Vec::new())).unwrap();
+    assert_eq!(report.warnings(), expected);
+    assert_eq!(corrected.into_inner(), original);
+}
+
+#[test]
+pub fn validate_ivf_header_with_corrupt_frames() {
+    let mut ivf = Ivf::new(File::open("tests/data/320-24-crf.ivf").unwrap()).unwrap();
+    let mut writer = IvfWriter::new(Cursor::new(Vec::new()), 320, 180, 24, 1).unwrap();
+    while let Some(mut frame) = ivf.read_frame().unwrap() {
+        if frame.timestamp == 3 || frame.timestamp == 7 {
+            frame.packet = vec![0xFF; 16];
+        }
+        writer.write_frame(&frame).unwrap();
+    }
+    let data = writer.finish().unwrap().into_inner();
+
+    let expected = [
+        HeaderWarning::CorruptFrame { frame: 3 },
+        HeaderWarning::CorruptFrame { frame: 7 },
+    ];
+    let report = Ivf::new(Cursor::new(&data))
+        .unwrap()
+        .validate_header()
+        .unwrap();
+    assert_eq!(report.warnings(), expected);
+    assert_eq!(report.frame_count(), 24);
+    assert_eq!(report.width(), Some(320));
+
+    let report = IvfSlice::new(&data).unwrap().validate_header().unwrap();
+    assert_eq!(report.warnings(), expected);
+}
+
+#[test]
+pub fn iterate_vp9_stream() {
+    for path in FILES {
+        let data = fs::read(path).unwrap();
+        let frames = read_all(&mut Ivf::new(Cursor::new(&data)).unwrap()).unwrap();
+        let stream = Vp9Stream::new(Cursor::new(&data)).unwrap();
+        let stream_frames: Vec<StreamFrame> = stream.map(|frame| frame.unwrap()).collect();
+
+        let mut parser = Vp9Parser::default();
+        let mut expected = Vec::new();
+        for frame in &frames {
+            for (index, parsed) in parser.parse_packet(&frame.packet).enumerate() {
+                expected.push((frame, index, parsed.unwrap()));
+            }
+        }
+
+        assert_eq!(stream_frames.len(), expected.len());
+        for (stream_frame, (frame, index, parsed)) in stream_frames.iter().zip(expected) {
+            assert_eq!(stream_frame.timestamp, frame.timestamp);
+            assert_eq!(stream_frame.index, index);
+            assert_eq!(
+                stream_frame.visible,
+                parsed.show_frame() || parsed.show_existing_frame()
+            );
+            assert_eq!(
+                UncompressedHeader::from(&stream_frame.frame),
+                UncompressedHeader::from(&parsed)
+            );
+            assert_eq!(
+                &frame.packet[stream_frame.range.clone()],
+                stream_frame.frame.data()
+            );
+            assert_eq!(stream_frame.frame.data(), parsed.data());
+        }
+    }
+
+    // Frames of a super frame have consecutive ranges.
+    let data = fs::read("tests/data/320-24-cq.ivf").unwrap();
+    let stream_frames: Vec<StreamFrame> = Vp9Stream::new(Cursor::new(&data))
+        .unwrap()
+        .map(|frame| frame.unwrap())
+        .collect();
+    let hidden = stream_frames
+        .iter()
+        .position(|frame| !frame.visible)
+        .unwrap();
+    assert_eq!(stream_frames[hidden].range.start, 0);
+    assert_eq!(stream_frames[hidden + 1].index, 1);
+    assert_eq!(
+        stream_frames[hidden + 1].range.start,
+        stream_frames[hidden].range.end
+    );
+
+    let mut other = IvfWriter::with_four_cc(Cursor::new(Vec::new()), FourCc::Av1, 1, 1, 1, 1)
+        .unwrap()
+        .finish()
+        .unwrap();
+    other.set_position(0);
+    assert!(matches!(
+        Vp9Stream::new(other),
+        Err(IvfError::UnsupportedFourCc(FourCc::Av1))
+    ));
+}
+
+#[test]
+pub fn share_owned_frames() {
+    let data = fs::read("tests/data/320-24-cq.ivf").unwrap();
+    let frames = read_all(&mut Ivf::new(Cursor::new(&data)).unwrap()).unwrap();
+
+    let mut parser = Vp9Parser::default();
+    let mut shared_parser = Vp9Parser::default();
+    let mut owned_frames = Vec::new();
+    for frame in &frames {
+        let first = owned_frames.len();
+        let packet: Arc<[u8]> = Arc::from(frame.packet.as_slice());
+        let shared = shared_parser.parse_shared_packet(packet.clone());
+        let borrowed: Vec<_> = parser.parse_packet(&frame.packet).collect();
+        assert_eq!(shared.len(), borrowed.len());
+
+        for (shared, borrowed) in shared.into_iter().zip(borrowed) {
+            let (shared, borrowed) = (shared.unwrap(), borrowed.unwrap());
+            assert_eq!(shared.data(), borrowed.data());
+            assert_eq!(shared.tile_data(), borrowed.tile_data());
+            assert_eq!(
+                shared.compressed_header_data(),
+                borrowed.compressed_header_data()
+            );
+            assert_eq!(
+                UncompressedHeader::from(&shared),
+                UncompressedHeader::from(&borrowed)
+            );
+
+            let owned = borrowed.into_owned();
+            assert_eq!(owned.data(), shared.data());
+            assert_eq!(owned.frame_type(), shared.frame_type());
+            owned_frames.push(shared);
+        }
+        // All frames of the packet share its buffer.
+        assert_eq!(Arc::strong_count(&packet), 1 + owned_frames.len() - first);
+    }
+
+    // Owned frames can be moved to other threads.
+    let sizes = std::thread::spawn(move || {
+        owned_frames
+            .iter()
+            .map(|frame| frame.tile_data().len())
+            .sum::<usize>()
+    })
+    .join()
+    .unwrap();
+    assert!(sizes > 0);
+}
+
+#[test]
+pub fn store_frame_headers() {
+    let data = fs::read("tests/data/320-444-10bit.ivf").unwrap();
+    let frames = read_all(&mut Ivf::new(Cursor::new(&data)).unwrap()).unwrap();
+
+    let mut parser = Vp9Parser::default();
+    let mut headers: Vec<FrameHeader> = Vec::new();
+    for frame in &frames {
+        for parsed in parser.parse_packet(&frame.packet) {
+            let parsed = parsed.unwrap();
+            let header = *parsed.header();
+            assert_eq!(header.frame_type, parsed.frame_type());
+            assert_eq!(header.frame_size.width, parsed.width());
+            assert_eq!(header.frame_size.height, parsed.height());
+            assert_eq!(header.color_config.subsampling(), parsed.subsampling());
+            assert_eq!(
+                header.loop_filter.loop_filter_level,
+                parsed.loop_filter_level()
+            );
+            assert_eq!(header.quantization.base_q_idx, parsed.base_q_idx());
+            assert_eq!(
+                header.segmentation.segmentation_enabled,
+                parsed.segmentation_enabled()
+            );
+            assert_eq!(header.tile_info.tile_cols_log2, parsed.tile_cols_log2());
+            assert_eq!(header.tile_size, parsed.tile_size());
+            assert_eq!(
+                UncompressedHeader::from(&header),
+                UncompressedHeader::from(&parsed)
+            );
+            headers.push(header);
+        }
+    }
+
+    // The headers outlive the packets.
+    drop(frames);
+    assert_eq!(headers[0].frame_type, FrameType::KeyFrame);
+    assert_eq!(headers[0].color_config.color_depth, ColorDepth::Depth10);
+    assert_eq!(headers[0].color_config.subsampling(), Subsampling::Yuv444);
+}
+
+#[test]
+pub fn store_show_existing_frame_header() {
+    let data = fs::read("tests/data/320-24-cq.ivf").unwrap();
+    let frames = read_all(&mut Ivf::new(Cursor::new(&data)).unwrap()).unwrap();
+
+    // Parse up to the first hidden frame, whose values must not leak into the header.
+    let mut parser = Vp9Parser::default();
+    let mut hidden = None;
+    for frame in &frames {
+        for parsed in parser.parse_packet(&frame.packet) {
+            let parsed = parsed.unwrap();
+            if !parsed.show_frame() {
+                hidden = Some(*parsed.header());
+                break;
+            }
+        }
+        if hidden.is_some() {
+            break;
+        }
+    }
+    let hidden = hidden.unwrap();
+    assert_ne!(hidden.refresh_frame_flags, 0);
+    assert_ne!(hidden.loop_filter.loop_filter_level, 0);
+
+    let packet = show_existing_frame(Profile::Profile0, 2).unwrap();
+    let frame = parser.parse_packet(&packet).next().unwrap().unwrap();
+    let header = *frame.header();
+    assert!(header.show_existing_frame);
+    assert_eq!(header.frame_to_show_map_idx, Some(2));
+    assert!(header.show_frame);
+    assert_eq!(header.refresh_frame_flags, 0);
+    assert_eq!(header.loop_filter.loop_filter_level, 0);
+    assert_eq!(header.uncompressed_header_size, packet.len());
+    assert_eq!(header.compressed_header_size, 0);
+    assert_eq!(header.tile_size, 0);
+}